@@ -1,11 +1,28 @@
 use anyhow::{Context, Result};
 use std::path::Path;
 
+use crate::error::NopeError;
+use crate::util::git::parse_abbrev_oid;
+
 pub fn run(args: &[String]) -> Result<()> {
     let repo = git2::Repository::discover(".").context("Failed to discover repository")?;
 
     // Skip the applet name (argv[0])
-    let paths: Vec<&String> = args.iter().skip(1).collect();
+    let mut paths: Vec<&String> = Vec::new();
+    // `--expect-blob <sha> <path>`: stage <path> only if its content hashes to <sha>.
+    let mut expected_blobs: Vec<(&String, &String)> = Vec::new();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--expect-blob" {
+            let (Some(sha), Some(path)) = (iter.next(), iter.next()) else {
+                anyhow::bail!("Expected <sha> <path> after --expect-blob");
+            };
+            expected_blobs.push((sha, path));
+            paths.push(path);
+        } else {
+            paths.push(arg);
+        }
+    }
 
     if paths.is_empty() {
         println!("GitAdd requires explicit paths. Use GitAddDot to stage '.' or GitAddAll for all changes.");
@@ -17,6 +34,7 @@ pub fn run(args: &[String]) -> Result<()> {
         .workdir()
         .context("Repository has no working directory")?;
 
+    let mut staged = Vec::new();
     for path_str in paths {
         let path = Path::new(path_str);
 
@@ -44,9 +62,33 @@ pub fn run(args: &[String]) -> Result<()> {
         index
             .add_path(rel_path)
             .with_context(|| format!("Failed to add path to index: {}", path_str))?;
-        println!("Staged: {}", rel_path.display());
+
+        // Checked against the staged entry, so clean/eol filters apply exactly as
+        // they will in the commit. Nothing is written unless every check passes.
+        for (sha, _) in expected_blobs.iter().filter(|(_, p)| *p == path_str) {
+            let entry = index
+                .get_path(rel_path, 0)
+                .with_context(|| format!("Path was not staged: {}", path_str))?;
+            check_expected_blob(&entry.id, sha, path_str)?;
+        }
+        staged.push(rel_path.to_path_buf());
     }
 
     index.write().context("Failed to write index to disk")?;
+    for rel_path in staged {
+        println!("Staged: {}", rel_path.display());
+    }
     Ok(())
 }
+
+/// Fail unless `actual` starts with `expected`, a full or abbreviated blob id.
+fn check_expected_blob(actual: &git2::Oid, expected: &str, path: &str) -> Result<()> {
+    let expected = parse_abbrev_oid(expected).with_context(|| format!("Invalid --expect-blob for {path}"))?;
+    if actual.to_string().starts_with(&expected) {
+        return Ok(());
+    }
+    Err(NopeError::ConcurrentUpdate(format!(
+        "{path} hashes to {actual}, expected {expected}; nothing was staged"
+    ))
+    .into())
+}
@@ -94,7 +94,7 @@ fn print_status_line(repo: &Repository, cleanliness: &Cleanliness, colors: &Colo
     let commit = head.peel_to_commit().context("Failed to get HEAD commit")?;
     let short_oid = &commit.id().to_string()[..7];
 
-    let remote_info = get_remote_origin(&repo)?;
+    let remote_info = get_remote_origin(repo)?;
 
     println!(
         "{} | {} | {} | {}",
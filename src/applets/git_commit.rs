@@ -1,20 +1,93 @@
 use anyhow::{Context, Result};
-use gix::bstr::{BStr, ByteSlice};
-use gix::object::tree::EntryKind;
+use gix::bstr::ByteSlice;
+use gix::objs::WriteTo;
 use gix::Repository;
-use gix_index::entry::{Flags, Mode, Stage};
-use gix_index::File as IndexFile;
+use serde::Serialize;
+use std::ffi::OsStr;
+
+use crate::error::NopeError;
+use crate::util::budget::check_budget;
+use crate::util::cache_tree;
+use crate::util::date::{format_iso_strict, format_offset, signature_time};
+use crate::util::diffstat::{diff_trees, FileStat};
+use crate::util::git::{open_for_tree_diffs, parse_abbrev_oid};
+use crate::util::hooks::Hooks;
+use crate::util::identity::{self, Identity, Role, FALLBACK_EMAIL, FALLBACK_NAME};
+use crate::util::journal::Journal;
+use crate::util::lint::lint_message;
+use crate::util::message::{read_message_source, Cleanup, StructuredMessage};
+use crate::util::policy::CommitPolicy;
+use crate::util::secrets::Scanner;
+use crate::util::signing::Signer;
+use crate::util::trailers::{merge_trailers, parse_trailers, Trailer};
+use crate::VERSION;
+
+struct CommitArgs {
+    /// `None` only with `--amend`, which then reuses HEAD's message.
+    message: Option<String>,
+    no_verify: bool,
+    allow_empty: bool,
+    signoff: bool,
+    /// `-S` / `--no-gpg-sign`; `None` defers to `commit.gpgsign`.
+    gpg_sign: Option<bool>,
+    amend: bool,
+    /// `--expect-head`: full or abbreviated commit id HEAD must point at.
+    expect_head: Option<String>,
+    /// `--json`: print a [`CommitReport`] instead of `Created commit <id>`.
+    json: bool,
+}
+
+/// What `--json` prints: one object describing the commit just made.
+#[derive(Serialize)]
+struct CommitReport {
+    commit: String,
+    tree: String,
+    parents: Vec<String>,
+    branch: String,
+    author: SignatureReport,
+    committer: SignatureReport,
+    subject: String,
+    trailers: Vec<Trailer>,
+    files: Vec<FileStat>,
+}
+
+#[derive(Serialize)]
+struct SignatureReport {
+    name: String,
+    email: String,
+    date: String,
+    timestamp: i64,
+    timezone: String,
+}
+
+impl From<&gix::actor::Signature> for SignatureReport {
+    fn from(signature: &gix::actor::Signature) -> Self {
+        Self {
+            name: signature.name.to_str_lossy().into_owned(),
+            email: signature.email.to_str_lossy().into_owned(),
+            date: format_iso_strict(&signature.time),
+            timestamp: signature.time.seconds,
+            timezone: format_offset(signature.time.offset),
+        }
+    }
+}
 
 pub fn run(args: &[String]) -> Result<()> {
     let repo = gix::discover(".")?;
-    let message = parse_commit_message(args)?;
-
-    let mut index = repo
-        .open_index()
-        .context("Failed to open index (nothing staged?)")?;
+    let opts = parse_args(args)?;
+    let policy = CommitPolicy::from_repo(&repo)?;
 
-    let tree_id = write_tree_from_index(&repo, &mut index)?;
+    if opts.no_verify && !policy.allow_skip_hooks {
+        return Err(NopeError::PolicyRefusal(
+            "GitCommit --no-verify is disabled by policy (set nope.hooks.allowSkip to permit it)"
+                .to_string(),
+        )
+        .into());
+    }
 
+    // The branch tip is read once, here; the final ref update is a
+    // compare-and-swap against it, so a commit made meanwhile by another
+    // process is never overwritten.
     let mut head = repo.head()?;
     if head.is_detached() {
         anyhow::bail!("Detached HEAD is not supported for GitCommit");
@@ -29,33 +102,324 @@ pub fn run(args: &[String]) -> Result<()> {
         .to_str()
         .context("Branch name is not valid UTF-8")?;
 
-    let parents = if head.is_unborn() {
-        Vec::new()
+    let head_commit = if head.is_unborn() {
+        None
     } else {
-        vec![head.peel_to_commit_in_place()?.id]
+        Some(head.peel_to_commit_in_place()?)
+    };
+    if let Some(expected) = &opts.expect_head {
+        check_expected_head(head_commit.as_ref().map(|c| c.id), expected)?;
+    }
+
+    // An amend keeps HEAD's author, so only the committer needs configuring.
+    let author = match opts.amend {
+        true => None,
+        false => Some(resolve_identity(&repo, &policy, Role::Author)?),
     };
+    let committer = resolve_identity(&repo, &policy, Role::Committer)?;
+
+    let hooks = Hooks::from_repo(&repo)?;
+    if !opts.no_verify {
+        hooks.run("pre-commit", &[])?;
+    }
 
-    let (name, email) = signature_from_config(&repo);
-    let signature = gix::actor::SignatureRef {
-        name: BStr::new(name.as_bytes()),
-        email: BStr::new(email.as_bytes()),
-        time: gix::date::Time::now_local_or_utc(),
+    // Read after pre-commit so anything the hook re-staged is included.
+    let tree_id = cache_tree::write_index_tree(&repo)?.id;
+
+    // With --amend the new commit replaces HEAD, so it hangs off HEAD's parent.
+    let amended = match (&head_commit, opts.amend) {
+        (Some(commit), true) => Some(check_amendable(&repo, commit)?),
+        (None, true) => anyhow::bail!("GitCommit --amend requires an existing commit on the branch"),
+        (_, false) => None,
     };
 
-    let commit_id = repo.commit_as(
-        signature,
-        signature,
-        ref_name_str,
-        &message,
-        tree_id,
-        parents,
-    )?;
-    println!("Created commit {commit_id}");
+    let base = match &amended {
+        Some(amended) => amended.parent.clone(),
+        None => head_commit.clone(),
+    };
+    let (parents, parent_tree_id) = match &base {
+        None => (Vec::new(), repo.empty_tree().id),
+        Some(parent) => (vec![parent.id], parent.tree_id()?.detach()),
+    };
+
+    if tree_id == parent_tree_id {
+        if !opts.allow_empty {
+            return Err(NopeError::NothingToCommit.into());
+        }
+        if !policy.allow_empty {
+            return Err(NopeError::PolicyRefusal(
+                "GitCommit --allow-empty is disabled by policy (set nope.commit.allowEmpty to permit it)"
+                    .to_string(),
+            )
+            .into());
+        }
+    }
+
+    let stats = if policy.budget.has_limits() || opts.json {
+        diff_trees(&repo, parent_tree_id, tree_id)?
+    } else {
+        Vec::new()
+    };
+    if let Some(report) = check_budget(&stats, &policy.budget) {
+        return Err(NopeError::PolicyRefusal(report).into());
+    }
+
+    if policy.scan_secrets {
+        scan_staged_changes(&repo, &policy, parent_tree_id, tree_id)?;
+    }
+
+    let base_message = match (&opts.message, &amended) {
+        (Some(message), _) => message.clone(),
+        (None, Some(amended)) => amended.message.clone(),
+        (None, None) => anyhow::bail!("GitCommit requires -m/--message, -F/--file or --message-json"),
+    };
+    let message = run_message_hooks(&repo, &hooks, &base_message, &opts)?;
+    check_message(&message, &policy)?;
+
+    let message = merge_trailers(&message, &provenance_trailers(&policy, &opts, &committer));
+    let author_time = signature_time("GIT_AUTHOR_DATE", policy.pinned_date.as_deref())?;
+    let committer_time = signature_time("GIT_COMMITTER_DATE", policy.pinned_date.as_deref())?;
+
+    let mut commit = gix::objs::Commit {
+        message: message.into(),
+        tree: tree_id,
+        author: match &amended {
+            Some(amended) => amended.author.clone(),
+            None => to_signature(author.as_ref().expect("resolved unless amending"), author_time),
+        },
+        committer: to_signature(&committer, committer_time),
+        encoding: None,
+        parents: parents.into(),
+        extra_headers: Vec::new(),
+    };
+
+    let sign = opts.gpg_sign.unwrap_or_else(|| {
+        repo.config_snapshot()
+            .boolean("commit.gpgsign")
+            .unwrap_or(false)
+    });
+    if sign {
+        let mut payload = Vec::new();
+        commit.write_to(&mut payload)?;
+        let gpgsig = Signer::from_repo(&repo)?.sign(&payload, &committer.to_ident())?;
+        commit.extra_headers.push(("gpgsig".into(), gpgsig.into()));
+    }
+
+    let operation = if amended.is_some() { "commit (amend)" } else { "commit" };
+    let previous = head_commit.as_ref().map(|c| c.id);
+    let commit_id = write_commit(&repo, &commit, ref_name_str, previous, operation)?;
+    // The branch has moved: from here on nothing may fail the commit, or a
+    // caller retrying it would commit twice.
+    if let Err(err) = Journal::new(repo.common_dir()).record(&commit_id) {
+        eprintln!("Warning: commit {commit_id} was not recorded in the journal, so --amend will refuse it: {err:#}");
+    }
+    if opts.json {
+        let message = commit.message.to_str_lossy();
+        let report = CommitReport {
+            commit: commit_id.to_string(),
+            tree: commit.tree.to_string(),
+            parents: commit.parents.iter().map(|p| p.to_string()).collect(),
+            branch: ref_name.shorten().to_str_lossy().into_owned(),
+            author: (&commit.author).into(),
+            committer: (&commit.committer).into(),
+            subject: message.lines().next().unwrap_or("").to_string(),
+            trailers: parse_trailers(&message),
+            files: stats,
+        };
+        println!("{}", serde_json::to_string(&report)?);
+    } else {
+        println!("Created commit {commit_id}");
+    }
+
+    // Like git, a failing post-commit hook cannot undo the commit.
+    if let Err(err) = hooks.run("post-commit", &[]) {
+        eprintln!("Warning: {err}");
+    }
     Ok(())
 }
 
-fn parse_commit_message(args: &[String]) -> Result<String> {
+/// Refuse the commit if lines added between `old_tree` and `new_tree` look like credentials.
+fn scan_staged_changes(
+    repo: &Repository,
+    policy: &CommitPolicy,
+    old_tree: gix::ObjectId,
+    new_tree: gix::ObjectId,
+) -> Result<()> {
+    let workdir = repo.work_dir().context("Repository has no working directory")?;
+    let scanner = Scanner::with_allowlist_file(&workdir.join(&policy.secrets_allowlist))?;
+
+    let git2_repo = open_for_tree_diffs(repo)?;
+    let old_tree = git2_repo.find_tree(git2::Oid::from_bytes(old_tree.as_bytes())?)?;
+    let new_tree = git2_repo.find_tree(git2::Oid::from_bytes(new_tree.as_bytes())?)?;
+    let diff = git2_repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+    let mut findings = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            if line.origin() == '+' {
+                if let (Some(path), Some(line_no)) = (delta.new_file().path(), line.new_lineno()) {
+                    let text = String::from_utf8_lossy(line.content());
+                    findings.extend(scanner.scan_line(
+                        &path.to_string_lossy(),
+                        line_no as usize,
+                        text.trim_end_matches(['\r', '\n']),
+                    ));
+                }
+            }
+            true
+        }),
+    )?;
+
+    if findings.is_empty() {
+        return Ok(());
+    }
+    let mut report = String::from("Commit blocked: staged changes look like they contain secrets:");
+    for finding in &findings {
+        report.push_str("\n  ");
+        report.push_str(&finding.to_string());
+    }
+    report.push_str(&format!(
+        "\nIf these are false positives, add their fingerprints to {}.",
+        policy.secrets_allowlist
+    ));
+    Err(NopeError::PolicyRefusal(report).into())
+}
+
+/// The parts of HEAD that an amend carries over.
+struct Amended<'repo> {
+    parent: Option<gix::Commit<'repo>>,
+    author: gix::actor::Signature,
+    message: String,
+}
+
+/// Allow `--amend` only for HEAD commits GitCommit made, that no
+/// remote-tracking ref contains, and that have exactly one parent. Only the
+/// journal proves authorship: a `Generated-By` trailer can be written by anyone.
+fn check_amendable<'repo>(repo: &'repo Repository, head: &gix::Commit<'repo>) -> Result<Amended<'repo>> {
+    let refuse = |why: &str| -> anyhow::Error {
+        NopeError::PolicyRefusal(format!("GitCommit --amend refused: {why}")).into()
+    };
+
+    let decoded = head.decode()?;
+    let message = decoded.message.to_str_lossy().into_owned();
+
+    if !Journal::new(repo.common_dir()).contains(&head.id) {
+        return Err(refuse("HEAD was not created by GitCommit"));
+    }
+
+    let parent_ids: Vec<gix::ObjectId> = decoded.parents().collect();
+    if parent_ids.len() != 1 {
+        return Err(refuse("HEAD must have exactly one parent"));
+    }
+
+    let references = repo.references()?;
+    for mut reference in references.remote_branches()?.flatten() {
+        let Ok(tip) = reference.peel_to_commit().map(|c| c.id) else {
+            continue;
+        };
+        // HEAD is contained in `tip` exactly when it is their merge base.
+        let contained = match repo.merge_base(tip, head.id) {
+            Ok(base) => base == head.id,
+            Err(gix::repository::merge_base::Error::NotFound { .. }) => false,
+            Err(err) => return Err(err.into()),
+        };
+        if contained {
+            let name = reference.name().shorten().to_str_lossy().into_owned();
+            return Err(refuse(&format!("HEAD is already contained in {name}")));
+        }
+    }
+
+    Ok(Amended {
+        parent: Some(repo.find_commit(parent_ids[0])?),
+        author: decoded.author().into(),
+        message,
+    })
+}
+
+/// Write `commit` and move `reference` from `previous` to it, recording an
+/// `<operation>:` reflog entry. The update fails if the branch moved meanwhile.
+fn write_commit(
+    repo: &Repository,
+    commit: &gix::objs::Commit,
+    reference: &str,
+    previous: Option<gix::ObjectId>,
+    operation: &str,
+) -> Result<gix::ObjectId> {
+    use gix::refs::transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog};
+    use gix::refs::Target;
+
+    let commit_id = repo.write_object(commit)?.detach();
+    repo.edit_reference(RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: gix::reference::log::message(
+                    operation,
+                    commit.message.as_ref(),
+                    commit.parents.len(),
+                ),
+            },
+            expected: match previous {
+                Some(previous) => PreviousValue::ExistingMustMatch(Target::Object(previous)),
+                None => PreviousValue::MustNotExist,
+            },
+            new: Target::Object(commit_id),
+        },
+        name: reference.try_into()?,
+        deref: true,
+    })
+    .map_err(|err| {
+        let current = repo
+            .try_find_reference(reference)
+            .ok()
+            .flatten()
+            .and_then(|mut r| r.peel_to_id_in_place().ok())
+            .map(|id| id.detach());
+        if current == previous {
+            return anyhow::Error::from(err);
+        }
+        let found = current.map_or_else(|| "missing".to_string(), |id| id.to_string());
+        let expected = previous.map_or_else(|| "unborn".to_string(), |id| id.to_string());
+        NopeError::ConcurrentUpdate(format!(
+            "{reference} moved to {found} while committing (expected {expected}); nothing was committed"
+        ))
+        .into()
+    })?;
+    Ok(commit_id)
+}
+
+/// Fail unless HEAD points at `expected`, a full or abbreviated commit id.
+fn check_expected_head(head: Option<gix::ObjectId>, expected: &str) -> Result<()> {
+    let expected = parse_abbrev_oid(expected).context("Invalid --expect-head")?;
+    match head {
+        Some(id) if id.to_string().starts_with(&expected) => Ok(()),
+        Some(id) => Err(NopeError::ConcurrentUpdate(format!(
+            "HEAD is at {id}, expected {expected}"
+        ))
+        .into()),
+        None => Err(NopeError::ConcurrentUpdate(format!(
+            "HEAD has no commits yet, expected {expected}"
+        ))
+        .into()),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<CommitArgs> {
     let mut messages = Vec::new();
+    let mut file = None;
+    let mut json_file = None;
+    let mut cleanup = Cleanup::default();
+    let mut no_verify = false;
+    let mut allow_empty = false;
+    let mut signoff = false;
+    let mut gpg_sign = None;
+    let mut amend = false;
+    let mut expect_head = None;
+    let mut json = false;
     let mut iter = args.iter().skip(1).peekable();
     while let Some(arg) = iter.next() {
         match arg.as_str() {
@@ -65,54 +429,180 @@ fn parse_commit_message(args: &[String]) -> Result<String> {
                     .ok_or_else(|| anyhow::anyhow!("Expected message after {arg}"))?;
                 messages.push(msg.to_string());
             }
+            "-F" | "--file" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Expected file after {arg}"))?;
+                file = Some(path.to_string());
+            }
+            "--message-json" => {
+                let path = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Expected file after {arg}"))?;
+                json_file = Some(path.to_string());
+            }
+            "-n" | "--no-verify" => no_verify = true,
+            "--allow-empty" => allow_empty = true,
+            "-s" | "--signoff" => signoff = true,
+            "-S" | "--gpg-sign" => gpg_sign = Some(true),
+            "--no-gpg-sign" => gpg_sign = Some(false),
+            "--amend" => amend = true,
+            "--json" => json = true,
+            "--expect-head" => {
+                let oid = iter
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Expected commit id after {arg}"))?;
+                expect_head = Some(oid.to_string());
+            }
+            _ if arg.starts_with("--expect-head=") => {
+                expect_head = Some(arg["--expect-head=".len()..].to_string())
+            }
+            _ if arg.starts_with("--file=") => file = Some(arg["--file=".len()..].to_string()),
+            _ if arg.starts_with("--cleanup=") => cleanup = Cleanup::parse(&arg["--cleanup=".len()..])?,
             _ if arg.starts_with('-') => anyhow::bail!("Unsupported GitCommit flag: {arg}"),
             _ => {}
         }
     }
 
-    if messages.is_empty() {
-        anyhow::bail!("GitCommit requires -m/--message");
+    let message = match (messages.is_empty(), file, json_file) {
+        (true, None, None) if amend => None,
+        (true, None, None) => {
+            anyhow::bail!("GitCommit requires -m/--message, -F/--file or --message-json")
+        }
+        (false, None, None) => Some(messages.join("\n\n")),
+        (true, Some(path), None) => Some(read_message_source(&path)?),
+        (true, None, Some(path)) => {
+            Some(StructuredMessage::from_json(&read_message_source(&path)?)?.render())
+        }
+        _ => anyhow::bail!("Only one of -m, -F and --message-json may be used"),
+    };
+
+    let message = message.map(|m| cleanup.apply(&m));
+    if message.as_deref().is_some_and(|m| m.trim().is_empty()) {
+        anyhow::bail!("Aborting commit due to empty commit message");
     }
 
-    Ok(messages.join("\n\n"))
+    Ok(CommitArgs {
+        message,
+        no_verify,
+        allow_empty,
+        signoff,
+        gpg_sign,
+        amend,
+        expect_head,
+        json,
+    })
 }
 
-fn signature_from_config(repo: &Repository) -> (String, String) {
-    let config = repo.config_snapshot();
-    let name = config
-        .string("user.name")
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "agt".to_string());
-    let email = config
-        .string("user.email")
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "agt@local".to_string());
-    (name, email)
+/// Hand the message to prepare-commit-msg and commit-msg through
+/// `.git/COMMIT_EDITMSG`, returning whatever the hooks left in the file.
+fn run_message_hooks(
+    repo: &Repository,
+    hooks: &Hooks,
+    message: &str,
+    opts: &CommitArgs,
+) -> Result<String> {
+    let msg_file = repo.git_dir().join("COMMIT_EDITMSG");
+    std::fs::write(&msg_file, message)
+        .with_context(|| format!("Failed to write {}", msg_file.display()))?;
+
+    // Same source arguments git passes: "message" for -m/-F, "commit HEAD" when
+    // an amend reuses the previous message.
+    let source: &[&OsStr] = if opts.message.is_some() {
+        &[OsStr::new("message")]
+    } else {
+        &[OsStr::new("commit"), OsStr::new("HEAD")]
+    };
+    let mut hook_args = vec![msg_file.as_os_str()];
+    hook_args.extend_from_slice(source);
+    let mut ran = hooks.run("prepare-commit-msg", &hook_args)?;
+    if !opts.no_verify {
+        ran |= hooks.run("commit-msg", &[msg_file.as_os_str()])?;
+    }
+    if !ran {
+        return Ok(message.to_string());
+    }
+
+    let edited = std::fs::read_to_string(&msg_file)
+        .with_context(|| format!("Failed to read {}", msg_file.display()))?;
+    let edited = edited.trim_end().to_string();
+    if edited.trim().is_empty() {
+        anyhow::bail!("Aborting commit due to empty commit message");
+    }
+    Ok(edited)
 }
 
-fn write_tree_from_index(repo: &Repository, index: &mut IndexFile) -> Result<gix::ObjectId> {
-    let empty_tree_id = repo.write_object(gix_object::Tree::empty())?.detach();
-    let mut editor = repo.edit_tree(empty_tree_id)?;
+/// Trailers recording where a commit came from, per `nope.trailers.*` policy.
+fn provenance_trailers(policy: &CommitPolicy, opts: &CommitArgs, committer: &Identity) -> Vec<Trailer> {
+    let config = &policy.trailers;
+    let mut trailers = Vec::new();
 
-    for entry in index.entries().iter() {
-        if entry.stage() != Stage::Unconflicted {
-            anyhow::bail!("Cannot commit with unmerged paths present");
-        }
-        if entry.flags.contains(Flags::REMOVE) {
-            continue;
+    if let Ok(session) = std::env::var(&config.session_env) {
+        if !session.trim().is_empty() {
+            trailers.push(Trailer::new("Agent-Session", session.trim()));
         }
+    }
+    if config.generated_by {
+        trailers.push(Trailer::new("Generated-By", format!("git-nope/{VERSION}")));
+    }
+    for co_author in &config.co_authors {
+        trailers.push(Trailer::new("Co-authored-by", co_author.as_str()));
+    }
+    if opts.signoff || config.signoff {
+        trailers.push(Trailer::new("Signed-off-by", committer.to_ident()));
+    }
+    trailers
+}
 
-        let kind = match entry.mode {
-            Mode::FILE => EntryKind::Blob,
-            Mode::FILE_EXECUTABLE => EntryKind::BlobExecutable,
-            Mode::SYMLINK => EntryKind::Link,
-            Mode::COMMIT => EntryKind::Commit,
-            _ => continue,
-        };
+fn check_message(message: &str, policy: &CommitPolicy) -> Result<()> {
+    let violations = lint_message(message, &policy.lint);
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let mut report = String::from("Commit message rejected by lint policy:");
+    for violation in &violations {
+        report.push_str("\n  ");
+        report.push_str(&violation.to_string());
+    }
+    Err(NopeError::PolicyRefusal(report).into())
+}
 
-        let path = entry.path(index);
-        editor.upsert(path, kind, entry.id)?;
+/// The author or committer identity, checked against `nope.identity.*` policy.
+fn resolve_identity(repo: &Repository, policy: &CommitPolicy, role: Role) -> Result<Identity> {
+    let label = role.as_str();
+    let identity = match identity::resolve(repo, role) {
+        Some(identity) => identity,
+        None if policy.identity.require => {
+            return Err(NopeError::PolicyRefusal(format!(
+                "No {label} identity configured; set user.name and user.email (nope.identity.require is on)"
+            ))
+            .into());
+        }
+        None => {
+            eprintln!(
+                "Warning: no {label} identity configured, using {FALLBACK_NAME} <{FALLBACK_EMAIL}>"
+            );
+            Identity {
+                name: FALLBACK_NAME.to_string(),
+                email: FALLBACK_EMAIL.to_string(),
+            }
+        }
+    };
+    if !policy.identity.allows(&identity.email) {
+        return Err(NopeError::PolicyRefusal(format!(
+            "{label} email {} is not allowed by nope.identity policy",
+            identity.email
+        ))
+        .into());
     }
+    Ok(identity)
+}
 
-    Ok(editor.write()?.detach())
+fn to_signature(identity: &Identity, time: gix::date::Time) -> gix::actor::Signature {
+    gix::actor::Signature {
+        name: identity.name.as_str().into(),
+        email: identity.email.as_str().into(),
+        time,
+    }
 }
@@ -1,255 +1,792 @@
 use anyhow::{Context, Result};
-use git2::{Repository, Sort, Time};
-use std::collections::HashMap;
-use std::io::{IsTerminal, Write};
-use std::process::{Command, Stdio};
+use gix::prelude::ObjectIdExt;
+use gix::{ObjectId, Repository};
+use regex::Regex;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Component, PathBuf};
+
 use crate::util::color::ColorConfig;
+use crate::util::date::{format_iso_strict, format_offset, parse_git_date, DateMode};
+use crate::util::decoration::{self, Decoration};
+use crate::util::git::{open_for_tree_diffs, Revisions};
+use crate::util::graph::Graph;
+use crate::util::history::{self, Entry};
+use crate::util::pager::Output;
+use crate::util::patch::{self, OutputFormat};
+use crate::util::pretty::{self, CommitInfo, Person, Template};
+use crate::util::trailers::{parse_trailers, Trailer};
+use crate::util::walk::{Limit, TopoWalk};
 
-pub fn run(args: &[String]) -> Result<()> {
-    let mut no_colors = false;
-    let mut topology_mode = false;
+struct LogArgs {
+    no_colors: bool,
+    /// `-t`: walk every ref, shown one line per commit unless `--format` says otherwise.
+    topology_mode: bool,
+    pretty: Pretty,
+    max_count: Option<usize>,
+    skip: usize,
+    reverse: bool,
+    first_parent: bool,
+    /// `--left-right`: mark commits `<` or `>` by the side of `A...B` they are on.
+    left_right: bool,
+    filter: CommitFilter,
+    /// Revision arguments such as `main..topic`, resolved in `run`.
+    revisions: Vec<String>,
+    /// Pathspecs after `--`, as given; resolved against the work tree in `run`.
+    paths: Vec<String>,
+    follow: bool,
+    /// Change summaries shown after each commit.
+    format: OutputFormat,
+    merge_diff: MergeDiff,
+    /// `--json`: one [`CommitReport`] per line instead of the human formats.
+    json: bool,
+    /// `--date=<mode>`; without it, `log.date` from the git config applies.
+    date: Option<DateMode>,
+    /// `--no-pager`: write to stdout even when it is a terminal.
+    no_pager: bool,
+}
 
-    // Skip argv[0]
-    for arg in args.iter().skip(1) {
-        match arg.as_str() {
-            "--no-colors" => no_colors = true,
-            "-t" => topology_mode = true,
-            _ => {} // Ignore others for now
+/// How each commit's header is printed.
+enum Pretty {
+    /// git's default `medium` format, `Template::medium`.
+    Medium(Template),
+    /// `--oneline`: id, decorations and subject. `--format=oneline` shows the
+    /// full id, `--oneline` and `-t` an abbreviated one.
+    Oneline(Template),
+    /// `--format=<template>`. With `terminator` (git's `tformat:`, the default
+    /// for a bare template) every commit ends with a newline; otherwise
+    /// (`format:`) newlines only separate commits.
+    Template { template: Template, terminator: bool },
+}
+
+/// How merges show their changes, as git's `--diff-merges`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MergeDiff {
+    /// No diff for merges: git's default.
+    Off,
+    /// `-m`: a separate diff, and header, against each parent.
+    Separate,
+    /// `--cc`: one dense combined diff against all parents.
+    Combined,
+}
+
+/// Per-commit conditions from the command line. Unlike `-n`/`--skip`, these
+/// also decide which parents the graph draws lanes to, as in git.
+#[derive(Default)]
+struct CommitFilter {
+    since: Option<i64>,
+    until: Option<i64>,
+    /// `--author` patterns; a commit matches if any does.
+    authors: Vec<Regex>,
+    /// `--committer` patterns; a commit matches if any does.
+    committers: Vec<Regex>,
+    /// `Some(true)` for `--merges`, `Some(false)` for `--no-merges`.
+    merges: Option<bool>,
+}
+
+impl CommitFilter {
+    /// No conditions given: every commit passes without being read.
+    fn is_empty(&self) -> bool {
+        self.since.is_none()
+            && self.until.is_none()
+            && self.authors.is_empty()
+            && self.committers.is_empty()
+            && self.merges.is_none()
+    }
+
+    /// Older than `--since`; such commits also hide their ancestors.
+    fn is_too_old(&self, commit: &gix::Commit) -> Result<bool> {
+        Ok(match self.since {
+            Some(since) => commit.time()?.seconds < since,
+            None => false,
+        })
+    }
+
+    fn matches(&self, commit: &gix::Commit) -> Result<bool> {
+        let commit = commit.decode()?;
+        if self.until.is_some_and(|until| commit.committer.time.seconds > until) {
+            return Ok(false);
+        }
+        if let Some(merges) = self.merges {
+            if (commit.parents.len() > 1) != merges {
+                return Ok(false);
+            }
+        }
+        // Patterns for the same header are alternatives; different headers must all match.
+        let ident = |sig: gix::actor::SignatureRef| format!("{} <{}>", sig.name, sig.email);
+        if !self.authors.is_empty() {
+            let author = ident(commit.author);
+            if !self.authors.iter().any(|re| re.is_match(&author)) {
+                return Ok(false);
+            }
+        }
+        if !self.committers.is_empty() {
+            let committer = ident(commit.committer);
+            if !self.committers.iter().any(|re| re.is_match(&committer)) {
+                return Ok(false);
+            }
         }
+        Ok(true)
     }
+}
 
-    let colors = ColorConfig::from_env_and_flag(no_colors);
-    let repo = Repository::discover(".").context("Failed to discover repository")?;
+/// What `--json` prints for each commit, one object per line.
+#[derive(Serialize)]
+struct CommitReport {
+    commit: String,
+    short: String,
+    /// The parents the graph would connect, rewritten past hidden commits
+    /// when paths are given.
+    parents: Vec<String>,
+    author: SignatureReport,
+    committer: SignatureReport,
+    subject: String,
+    body: String,
+    trailers: Vec<Trailer>,
+    refs: Vec<RefReport>,
+}
 
-    let mut revwalk = repo.revwalk().context("Failed to create revwalk")?;
-    
-    if topology_mode {
-        revwalk.push_glob("refs/*").context("Failed to push refs")?;
-        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
-    } else {
-        revwalk.push_head().context("Failed to push HEAD")?;
-        revwalk.set_sorting(Sort::TIME)?;
+#[derive(Serialize)]
+struct SignatureReport {
+    name: String,
+    email: String,
+    date: String,
+    timestamp: i64,
+    timezone: String,
+}
+
+impl From<&Person> for SignatureReport {
+    fn from(person: &Person) -> Self {
+        Self {
+            name: person.name.clone(),
+            email: person.email.clone(),
+            date: format_iso_strict(&person.time),
+            timestamp: person.time.seconds,
+            timezone: format_offset(person.time.offset),
+        }
     }
+}
 
-    // Pager setup
-    let mut pager_process = if std::io::stdout().is_terminal() {
-        setup_pager()
-    } else {
-        None
+/// A decoration as `--json` reports it. `HEAD` carries the branch it points
+/// to, which is also listed on its own.
+#[derive(Serialize)]
+struct RefReport {
+    #[serde(rename = "type")]
+    kind: RefKind,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum RefKind {
+    Head,
+    Branch,
+    Remote,
+    Tag,
+    Stash,
+}
+
+impl From<&Decoration> for RefReport {
+    fn from(decoration: &Decoration) -> Self {
+        let (kind, target) = match decoration {
+            Decoration::Head { branch } => (RefKind::Head, branch.clone()),
+            Decoration::Branch(_) => (RefKind::Branch, None),
+            Decoration::Remote(_) => (RefKind::Remote, None),
+            Decoration::Tag(_) => (RefKind::Tag, None),
+            Decoration::Stash => (RefKind::Stash, None),
+        };
+        Self { kind, name: decoration.name().to_string(), target }
+    }
+}
+
+/// Commits to consider, each with the parents the graph connects it to and
+/// the paths its diff is limited to.
+type CommitSource<'r> = Box<dyn Iterator<Item = Result<Entry>> + 'r>;
+
+/// Per-run state shared by every printed commit.
+struct Printer<'a> {
+    repo: &'a Repository,
+    /// Changes are still rendered by libgit2; opened only when a diff format
+    /// is asked for.
+    diff_repo: Option<&'a git2::Repository>,
+    limit: Option<&'a Limit>,
+    decorations: &'a HashMap<ObjectId, Vec<Decoration>>,
+    opts: &'a LogArgs,
+    colors: &'a ColorConfig,
+    /// How the human formats show dates.
+    dates: &'a DateMode,
+    /// Whether the last `--format` text ended its own line, so a separator
+    /// after it gets a graph row of its own.
+    ended_line: Cell<bool>,
+}
+
+impl Printer<'_> {
+    /// The `--left-right` mark for a commit: `<` on the left side of
+    /// `A...B`, `>` otherwise.
+    fn mark(&self, id: &ObjectId) -> Option<&'static str> {
+        let left = self.limit.is_some_and(|limit| limit.is_left(id));
+        self.opts.left_right.then_some(if left { "<" } else { ">" })
+    }
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let opts = parse_args(args)?;
+
+    let colors = ColorConfig::from_env_and_flag(opts.no_colors);
+    let repo = gix::discover(".").context("Failed to discover repository")?;
+    let diff_repo = match opts.format.any() {
+        true => Some(open_for_tree_diffs(&repo)?),
+        false => None,
     };
 
-    let mut out: Box<dyn Write> = if let Some(ref mut child) = pager_process {
-        Box::new(child.stdin.take().unwrap())
+    // Each commit comes with the parents the graph should connect it to: its
+    // real parents, or with paths given, parents rewritten past the commits
+    // that history simplification hides.
+    let revisions = start_commits(&repo, &opts)?;
+    let limit = Limit::new(&repo, &revisions, opts.first_parent)?;
+    let commits: CommitSource = if opts.paths.is_empty() {
+        // The graph needs children before parents, so both modes walk in
+        // topological order, ties broken by commit date (git's --date-order).
+        // The walk is incremental: with a commit-graph, the first commits are
+        // printed before the rest of history is read.
+        let mut walk = TopoWalk::new(&repo, &revisions.include, limit.as_ref(), opts.first_parent)?;
+        Box::new(std::iter::from_fn(move || {
+            walk.next().map(|id| {
+                let id = id?;
+                let parents = walk.parents(id)?.to_vec();
+                Ok(Entry { id, parents, paths: Vec::new() })
+            })
+        }))
     } else {
-        Box::new(std::io::stdout())
+        let paths = opts
+            .paths
+            .iter()
+            .map(|path| repo_path(&repo, path))
+            .collect::<Result<Vec<_>>>()?;
+        let entries = history::path_limited(&repo, &revisions.include, limit.as_ref(), paths, opts.follow, opts.first_parent)?;
+        Box::new(entries.into_iter().map(Ok))
     };
 
-    // Cache refs for decoration
-    let decorations = load_decorations(&repo)?;
+    let dates = match &opts.date {
+        Some(mode) => mode.clone(),
+        None => match repo.config_snapshot().string("log.date") {
+            Some(value) => DateMode::parse(&value.to_string()).context("Invalid log.date")?,
+            None => DateMode::Default,
+        },
+    };
+    let decorations = decoration::load(&repo)?;
+    let printer = Printer {
+        repo: &repo,
+        diff_repo: diff_repo.as_ref(),
+        limit: limit.as_ref(),
+        decorations: &decorations,
+        opts: &opts,
+        colors: &colors,
+        dates: &dates,
+        ended_line: Cell::new(false),
+    };
+    // Like git, --reverse output has no graph: lanes cannot be drawn upwards.
+    // Neither has --json, which is for programs rather than people.
+    let graph = (!opts.reverse && !opts.json).then(|| Graph::new(colors));
+
+    let mut out = Output::start(Some(&repo), "log", opts.no_pager);
+    let result = show_log(&mut out, &repo, commits, graph, &printer);
+    out.finish(result)
+}
 
-    for oid_res in revwalk {
-        let oid = oid_res?;
+/// Print the commits that pass the filters, stopping at --max-count.
+fn show_log(
+    out: &mut Output,
+    repo: &Repository,
+    commits: CommitSource,
+    mut graph: Option<Graph<ObjectId>>,
+    printer: &Printer,
+) -> Result<()> {
+    let opts = printer.opts;
+
+    // Filters run as the walk yields commits, and the walk stops as soon as
+    // --max-count commits are out, so a short log never reads all of history.
+    let mut selected = Vec::new();
+    let mut skipped = 0;
+    // As in git, a commit older than --since hides its ancestors too, even
+    // ones with a later (skewed) date. The walk is topological, so every child
+    // is seen before its parents.
+    let mut too_old = HashSet::new();
+    for item in commits {
+        if opts.max_count.is_some_and(|max| selected.len() >= max) {
+            break;
+        }
+        let entry = item?;
+        let oid = entry.id;
         let commit = repo.find_commit(oid)?;
-        
-        // Linear mode for now (no graph lanes)
-        // Stretch goal: Add graph rendering here
-        
-        let refs = decorations.get(&oid).map(|v| v.as_slice()).unwrap_or(&[]);
-        
-        if topology_mode {
-            print_oneline(&mut out, &repo, &commit, refs, &colors)?;
-        } else {
-            print_full(&mut out, &commit, refs, &colors)?;
+        if opts.filter.is_too_old(&commit)? || too_old.contains(&oid) {
+            too_old.extend(entry.parents.iter().copied());
+            continue;
+        }
+        if !opts.filter.matches(&commit)? {
+            continue;
+        }
+        if skipped < opts.skip {
+            skipped += 1;
+            continue;
         }
+
+        if opts.reverse {
+            selected.push(entry);
+            continue;
+        }
+        if let Some(graph) = graph.as_mut() {
+            let parents = interesting_parents(repo, &entry.parents, printer, &too_old)?;
+            graph.update(oid, parents, printer.mark(&oid).unwrap_or("*"));
+        }
+        show_commit(out, graph.as_mut(), &commit, &entry, printer, !selected.is_empty())?;
+        selected.push(entry);
     }
 
-    // Ensure we flush and wait for pager if used
-    out.flush()?;
-    drop(out); // Close stdin of pager
-    
-    if let Some(mut child) = pager_process {
-        let _ = child.wait();
+    if opts.reverse {
+        for (shown, entry) in selected.iter().rev().enumerate() {
+            let commit = repo.find_commit(entry.id)?;
+            show_commit(out, None, &commit, entry, printer, shown > 0)?;
+        }
     }
 
     Ok(())
 }
 
-fn setup_pager() -> Option<std::process::Child> {
-    let pager_cmd = std::env::var("GIT_PAGER")
-        .or_else(|_| std::env::var("PAGER"))
-        .unwrap_or_else(|_| "less -R".to_string());
-
-    if pager_cmd.is_empty() || pager_cmd == "cat" {
-        return None;
+/// Parents the graph draws lanes to: those in the range that pass the
+/// filters, and with --first-parent only the first one.
+fn interesting_parents(
+    repo: &Repository,
+    candidates: &[ObjectId],
+    printer: &Printer,
+    too_old: &HashSet<ObjectId>,
+) -> Result<Vec<ObjectId>> {
+    let Printer { opts, limit, .. } = *printer;
+    let take = if opts.first_parent { 1 } else { usize::MAX };
+    let mut parents = Vec::new();
+    for &id in candidates.iter().take(take) {
+        if limit.is_some_and(|limit| !limit.contains(&id)) {
+            continue;
+        }
+        if opts.filter.is_empty() {
+            parents.push(id);
+            continue;
+        }
+        let parent = repo.find_commit(id)?;
+        if !too_old.contains(&id) && !opts.filter.is_too_old(&parent)? && opts.filter.matches(&parent)? {
+            parents.push(id);
+        }
     }
-
-    // Split command and args roughly
-    let mut parts = pager_cmd.split_whitespace();
-    let cmd = parts.next()?;
-    let args: Vec<&str> = parts.collect();
-
-    Command::new(cmd)
-        .args(args)
-        .stdin(Stdio::piped())
-        .spawn()
-        .ok()
+    Ok(parents)
 }
 
-fn load_decorations(repo: &Repository) -> Result<HashMap<git2::Oid, Vec<String>>> {
-    let mut map: HashMap<git2::Oid, Vec<String>> = HashMap::new();
-
-    // Helper to add ref
-    let mut add_ref = |name: &str, target: git2::Oid| {
-        map.entry(target).or_default().push(name.to_string());
-    };
-
-    // HEAD
-    if let Ok(head) = repo.head() {
-        if let Some(target) = head.target() {
-            if let Ok(resolved) = head.resolve() {
-                 if let Some(name) = resolved.shorthand() {
-                     add_ref(&format!("HEAD -> {}", name), target);
-                 } else {
-                     add_ref("HEAD", target);
-                 }
-            } else {
-                add_ref("HEAD", target);
+/// What to walk: the revisions given, plus every ref in topology mode (git's
+/// `--all`). Without either, HEAD.
+fn start_commits(repo: &Repository, opts: &LogArgs) -> Result<Revisions> {
+    let mut revisions = Revisions::default();
+    for arg in &opts.revisions {
+        revisions.push(repo, arg)?;
+    }
+    if opts.topology_mode {
+        let references = repo.references().context("Failed to list refs")?;
+        for reference in references.prefixed("refs/").context("Failed to list refs")? {
+            let mut reference = reference.map_err(|err| anyhow::anyhow!(err))?;
+            if let Ok(commit) = reference.peel_to_commit() {
+                if !revisions.include.contains(&commit.id) {
+                    revisions.include.push(commit.id);
+                }
             }
         }
+    } else if revisions.is_empty() {
+        let head = repo.head_commit().context("Failed to resolve HEAD")?;
+        revisions.include.push(head.id);
     }
+    Ok(revisions)
+}
 
-    // Local branches
-    if let Ok(branches) = repo.branches(Some(git2::BranchType::Local)) {
-        for b in branches {
-            if let Ok((branch, _)) = b {
-                if let (Some(name), Some(target)) = (branch.name().ok().flatten(), branch.get().target()) {
-                    // Avoid duplicating HEAD -> branch if we already handled it?
-                    // Git decorations show "HEAD -> main, origin/main".
-                    // Our logic above adds "HEAD -> main".
-                    // If we see "main" here, we should check if it's already covered?
-                    // Actually git shows (HEAD -> main, origin/main).
-                    // So we want "main" in the list too?
-                    // Wait, if HEAD points to main, we added "HEAD -> main".
-                    // If we add "main" again, we get "HEAD -> main, main". That's redundant.
-                    // Let's keep it simple: just dump all refs, but special case HEAD.
-                    
-                     // Simple check: is this ref already in the list via HEAD -> ...?
-                     // A bit hacky. Let's just add them and maybe dedup later?
-                     // Or better: Don't bake "HEAD ->" into the string value, handle formatting later.
-                     // But for MVP, let's just add strings.
-                     
-                     // We will filter out the branch name if "HEAD -> {name}" is present in the list?
-                     // Let's just add normal branches.
-                     add_ref(name, target);
-                }
+/// A pathspec relative to the current directory, as a path from the work tree
+/// root. The path need not exist, so it is normalised lexically; `""` is the
+/// whole tree.
+fn repo_path(repo: &Repository, spec: &str) -> Result<String> {
+    let workdir = repo
+        .work_dir()
+        .context("Repository has no working directory")?
+        .canonicalize()?;
+    let mut resolved = PathBuf::new();
+    for component in std::env::current_dir()?.canonicalize()?.join(spec).components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
             }
+            Component::CurDir => {}
+            other => resolved.push(other),
         }
     }
+    let relative = resolved
+        .strip_prefix(&workdir)
+        .with_context(|| format!("Path is outside of repository working directory: {spec}"))?;
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
 
-    // Remote branches
-    if let Ok(branches) = repo.branches(Some(git2::BranchType::Remote)) {
-        for b in branches {
-            if let Ok((branch, _)) = b {
-                 if let (Some(name), Some(target)) = (branch.name().ok().flatten(), branch.get().target()) {
-                    add_ref(name, target);
-                }
-            }
+/// Print a commit and, when requested, its changes: against its first parent,
+/// or for merges as `-m`/`--cc` ask.
+fn show_commit(
+    out: &mut dyn Write,
+    mut graph: Option<&mut Graph<ObjectId>>,
+    commit: &gix::Commit,
+    entry: &Entry,
+    printer: &Printer,
+    separator: bool,
+) -> Result<()> {
+    let Printer { diff_repo, opts, colors, .. } = *printer;
+    if opts.json {
+        return print_json(out, commit, entry, printer);
+    }
+    // --first-parent diffs merges against their first parent, as in git.
+    let parents = if opts.first_parent { &entry.parents[..entry.parents.len().min(1)] } else { &entry.parents[..] };
+    if !opts.format.any() || (parents.len() > 1 && opts.merge_diff == MergeDiff::Off) {
+        return show_header(out, graph, commit, &entry.parents, None, printer, separator);
+    }
+
+    let repo = diff_repo.context("Repository not opened for diffs")?;
+    let tree = repo.find_tree(git2_id(&commit.tree_id()?)?)?;
+    let parent_trees = parents
+        .iter()
+        .map(|id| Ok(repo.find_commit(git2_id(id)?)?.tree()?))
+        .collect::<Result<Vec<_>>>()?;
+
+    if parents.len() > 1 && opts.merge_diff == MergeDiff::Combined {
+        show_header(out, graph.as_deref_mut(), commit, &entry.parents, None, printer, separator)?;
+        // git separates the header from a combined diff even when it is empty.
+        write_graph_line(out, graph.as_deref_mut(), "")?;
+        let width = stat_width(graph.as_deref_mut());
+        let lines = patch::render_combined(repo, &parent_trees, &tree, &entry.paths, opts.format, width, colors)?;
+        return write_diff(out, graph, &lines);
+    }
+
+    // A root commit diffs against the empty tree; -m repeats the header, with
+    // the parent, for every parent the merge differs from.
+    let mut shown = false;
+    let olds: Vec<Option<&git2::Tree>> = if parent_trees.is_empty() {
+        vec![None]
+    } else {
+        parent_trees.iter().map(Some).collect()
+    };
+    for (n, old) in olds.into_iter().enumerate() {
+        let diff = patch::tree_diff(repo, old, &tree, &entry.paths)?;
+        if diff.deltas().len() == 0 {
+            continue;
         }
+        let from = (parents.len() > 1).then(|| parents[n]);
+        show_header(out, graph.as_deref_mut(), commit, &entry.parents, from, printer, separator || shown)?;
+        shown = true;
+        if !matches!(opts.pretty, Pretty::Oneline(_)) && !is_empty_template(&opts.pretty) {
+            // Like git, "---" instead of a blank line when a stat precedes a patch.
+            let dashes = if opts.format.stat && opts.format.patch { "---" } else { "" };
+            write_graph_line(out, graph.as_deref_mut(), dashes)?;
+        }
+        let width = stat_width(graph.as_deref_mut());
+        let lines = patch::render(repo, &diff, opts.format, width, colors)?;
+        write_diff(out, graph.as_deref_mut(), &lines)?;
+    }
+    if !shown {
+        show_header(out, graph, commit, &entry.parents, None, printer, separator)?;
     }
+    Ok(())
+}
 
-    // Tags
-    if let Ok(tags) = repo.references_glob("refs/tags/*") {
-        for tag in tags {
-            if let Ok(r) = tag {
-                if let Some(target) = r.target() {
-                     let name = r.shorthand().unwrap_or("?");
-                     add_ref(&format!("tag: {}", name), target);
+/// The commit header in the selected format; `from` names the parent a `-m`
+/// diff is against.
+fn show_header(
+    out: &mut dyn Write,
+    mut graph: Option<&mut Graph<ObjectId>>,
+    commit: &gix::Commit,
+    parents: &[ObjectId],
+    from: Option<ObjectId>,
+    printer: &Printer,
+    separator: bool,
+) -> Result<()> {
+    let Printer { repo, decorations, opts, colors, dates, ref ended_line, .. } = *printer;
+    let refs = decorations.get(&commit.id).cloned().unwrap_or_default();
+    let mut info = commit_info(repo, commit, parents, refs)?;
+    // The graph shows the --left-right mark in place of `*`.
+    info.mark = if graph.is_some() { None } else { printer.mark(&commit.id) };
+    info.from = from.map(|id| abbreviated(repo, id));
+    match &opts.pretty {
+        Pretty::Oneline(template) => {
+            let text = template.render(&info, colors, dates);
+            match graph {
+                Some(graph) => writeln!(out, "{}", graph.render_commit(&text))?,
+                None => writeln!(out, "{text}")?,
+            }
+            Ok(())
+        }
+        Pretty::Medium(template) => {
+            // Entries are separated by a blank line that continues the graph lanes.
+            if separator {
+                write_graph_line(out, graph.as_deref_mut(), "")?;
+            }
+            let text = template.render(&info, colors, dates);
+            match graph {
+                Some(graph) => write!(out, "{}", graph.render_commit(&text))?,
+                None => write!(out, "{text}")?,
+            }
+            Ok(())
+        }
+        Pretty::Template { template, terminator } => {
+            // As in git, `format:` puts a newline between commits and `tformat:`
+            // one after each, on a row of its own when the text already ended
+            // its line.
+            if !terminator && separator {
+                if ended_line.get() {
+                    write_graph_line(out, graph.as_deref_mut(), "")?;
+                } else {
+                    writeln!(out)?;
                 }
             }
+            let text = template.render(&info, colors, dates);
+            ended_line.set(text.ends_with('\n'));
+            match graph.as_deref_mut() {
+                Some(graph) => write!(out, "{}", graph.render_commit(&text))?,
+                None => write!(out, "{text}")?,
+            }
+            if *terminator && !template.is_empty() {
+                if text.ends_with('\n') {
+                    write_graph_line(out, graph, "")?;
+                } else {
+                    writeln!(out)?;
+                }
+            }
+            Ok(())
         }
     }
-    
-    // Dedup and format logic is complex to match git exactly.
-    // For now we might have "HEAD -> main", "main".
-    // We can clean this up in the print function or just live with it for MVP.
-    // Let's leave as is.
-
-    Ok(map)
-}
-
-fn format_decorations(refs: &[String], colors: &ColorConfig) -> String {
-    if refs.is_empty() {
-        return String::new();
-    }
-    
-    // Naive dedup: if we have "HEAD -> main" and "main", remove "main".
-    let mut final_refs = refs.to_vec();
-    
-    // Find if we have a "HEAD -> branch" entry
-    let head_target = final_refs.iter()
-        .find(|r| r.starts_with("HEAD -> "))
-        .map(|r| r.strip_prefix("HEAD -> ").unwrap().to_string());
-        
-    if let Some(target_branch) = head_target {
-        final_refs.retain(|r| r != &target_branch);
-    }
-    
-    let joined = final_refs.join(", ");
-    let styled = colors.paint(colors.yellow_style(), joined);
-    format!(" ({})", styled)
-}
-
-fn print_full(out: &mut Box<dyn Write>, commit: &git2::Commit, refs: &[String], colors: &ColorConfig) -> Result<()> {
-    let oid_str = commit.id().to_string();
-    let styled_oid = colors.paint(colors.yellow_style(), &oid_str);
-    let decorations = format_decorations(refs, colors);
-    
-    writeln!(out, "commit {}{}", styled_oid, decorations)?;
-    
-    let author = commit.author();
-    let name = author.name().unwrap_or("Unknown");
-    let email = author.email().unwrap_or("unknown");
-    
-    writeln!(out, "Author: {} <{}>", name, email)?;
-    
-    let time = commit.time();
-    let formatted_time = format_time(time);
-    writeln!(out, "Date:   {}", formatted_time)?;
-    
-    writeln!(out)?;
-    
-    if let Some(msg) = commit.message() {
-        for line in msg.lines() {
-            writeln!(out, "    {}", line)?;
-        }
-    }
-    
-    writeln!(out)?;
+}
+
+/// An empty `--format` prints nothing for the commit, not even the line
+/// that separates it from its diff.
+fn is_empty_template(pretty: &Pretty) -> bool {
+    matches!(pretty, Pretty::Template { template, .. } if template.is_empty())
+}
+
+/// The fields log formats show, with the parents the log connects `commit` to.
+fn commit_info(repo: &Repository, commit: &gix::Commit, parents: &[ObjectId], refs: Vec<Decoration>) -> Result<CommitInfo> {
+    let decoded = commit.decode()?;
+    let person = |signature: gix::actor::SignatureRef| Person {
+        name: signature.name.to_string(),
+        email: signature.email.to_string(),
+        time: signature.time,
+    };
+    Ok(CommitInfo {
+        id: abbreviated(repo, commit.id),
+        tree: abbreviated(repo, decoded.tree()),
+        parents: parents.iter().map(|&id| abbreviated(repo, id)).collect(),
+        author: person(decoded.author),
+        committer: person(decoded.committer),
+        message: decoded.message.to_string(),
+        refs,
+        mark: None,
+        from: None,
+    })
+}
+
+/// `id` in full and as git abbreviates it, honouring `core.abbrev`.
+fn abbreviated(repo: &Repository, id: ObjectId) -> pretty::Id {
+    pretty::Id { full: id.to_string(), short: id.attach(repo).shorten_or_id().to_string() }
+}
+
+/// The libgit2 id of a gix object, for the diffs libgit2 still renders.
+fn git2_id(id: &gix::oid) -> Result<git2::Oid> {
+    Ok(git2::Oid::from_bytes(id.as_bytes())?)
+}
+
+/// One line of `text` after the graph's continuing lanes.
+fn write_graph_line(out: &mut dyn Write, graph: Option<&mut Graph<ObjectId>>, text: &str) -> Result<()> {
+    match graph {
+        Some(graph) => writeln!(out, "{}{text}", graph.padding_line())?,
+        None => writeln!(out, "{text}")?,
+    }
     Ok(())
 }
 
-fn print_oneline(out: &mut Box<dyn Write>, _repo: &Repository, commit: &git2::Commit, refs: &[String], colors: &ColorConfig) -> Result<()> {
-    let short_oid = &commit.id().to_string()[..7];
-    let styled_oid = colors.paint(colors.yellow_style(), short_oid);
-    let decorations = format_decorations(refs, colors);
-    
-    let summary = commit.summary().unwrap_or("");
-    
-    writeln!(out, "{}{} {}", styled_oid, decorations, summary)?;
+fn write_diff(out: &mut dyn Write, mut graph: Option<&mut Graph<ObjectId>>, lines: &[String]) -> Result<()> {
+    for line in lines {
+        write_graph_line(out, graph.as_deref_mut(), line)?;
+    }
     Ok(())
 }
 
-fn format_time(time: Time) -> String {
-    // We don't have a chrono dep in the plan, so we'll do basic formatting or add chrono?
-    // The plan didn't strictly specify chrono, but git2 returns seconds since epoch.
-    // For MVP, just printing the raw timestamp or simple formatting is safer than adding deps mid-flight?
-    // Wait, Cargo.toml already has `chrono = "0.4"`.
-    // So we can use chrono.
-    
-    let dt = chrono::DateTime::from_timestamp(time.seconds(), 0);
-    if let Some(d) = dt {
-         d.format("%a %b %e %H:%M:%S %Y %z").to_string()
-    } else {
-        format!("{}", time.seconds())
+/// Columns `--stat` may use: the terminal width (`COLUMNS`, else 80 as git
+/// assumes off a terminal) less the graph drawn before each line.
+fn stat_width(graph: Option<&mut Graph<ObjectId>>) -> usize {
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&columns: &usize| columns > 0)
+        .unwrap_or(80);
+    // Byte length, colors included, as git measures it.
+    let prefix = graph.map_or(0, |graph| graph.padding_line().len());
+    columns.saturating_sub(prefix)
+}
+
+fn parse_args(args: &[String]) -> Result<LogArgs> {
+    let mut opts = LogArgs {
+        no_colors: false,
+        topology_mode: false,
+        pretty: Pretty::Medium(Template::medium()),
+        max_count: None,
+        skip: 0,
+        reverse: false,
+        first_parent: false,
+        left_right: false,
+        filter: CommitFilter::default(),
+        revisions: Vec::new(),
+        paths: Vec::new(),
+        follow: false,
+        format: OutputFormat::default(),
+        merge_diff: MergeDiff::Off,
+        json: false,
+        date: None,
+        no_pager: false,
+    };
+
+    let mut pretty = None;
+    // Skip argv[0]
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || -> Result<String> {
+            match &inline {
+                Some(value) => Ok(value.clone()),
+                None => iter
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Expected a value after {flag}")),
+            }
+        };
+        match flag {
+            "--no-colors" => opts.no_colors = true,
+            "-t" => opts.topology_mode = true,
+            "--oneline" => pretty = Some(Pretty::Oneline(Template::oneline(true))),
+            "--format" | "--pretty" => pretty = Some(parse_pretty(flag, &value()?)?),
+            "-n" | "--max-count" => opts.max_count = Some(count(flag, &value()?)?),
+            "--skip" => opts.skip = count(flag, &value()?)?,
+            "--since" | "--after" => opts.filter.since = Some(date_limit(flag, &value()?)?),
+            "--until" | "--before" => opts.filter.until = Some(date_limit(flag, &value()?)?),
+            "--author" => opts.filter.authors.push(pattern(flag, &value()?)?),
+            "--committer" => opts.filter.committers.push(pattern(flag, &value()?)?),
+            "--merges" => opts.filter.merges = Some(true),
+            "--no-merges" => opts.filter.merges = Some(false),
+            "--first-parent" => opts.first_parent = true,
+            "--left-right" => opts.left_right = true,
+            "--reverse" => opts.reverse = true,
+            "--follow" => opts.follow = true,
+            "--stat" => opts.format.stat = true,
+            "--numstat" => opts.format.numstat = true,
+            "--name-status" => opts.format.name_status = true,
+            "-p" | "-u" | "--patch" => opts.format.patch = true,
+            "-m" => opts.merge_diff = MergeDiff::Separate,
+            "--cc" => opts.merge_diff = MergeDiff::Combined,
+            "--json" => opts.json = true,
+            "--date" => opts.date = Some(DateMode::parse(&value()?)?),
+            "--no-pager" => opts.no_pager = true,
+            "--" => {
+                opts.paths.extend(iter.by_ref().cloned());
+                break;
+            }
+            // -<n> and -n<n>, as in git.
+            _ if flag
+                .strip_prefix('-')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) =>
+            {
+                opts.max_count = Some(count("-<n>", &flag[1..])?)
+            }
+            _ if flag.len() > 2 && flag.starts_with("-n") => {
+                opts.max_count = Some(count("-n", &flag[2..])?)
+            }
+            _ if flag.starts_with('-') => anyhow::bail!("Unsupported GitLog flag: {arg}"),
+            _ => opts.revisions.push(arg.clone()),
+        }
     }
+    // -t implies one line per commit, unless a format was asked for.
+    opts.pretty = match pretty {
+        Some(pretty) => pretty,
+        None if opts.topology_mode => Pretty::Oneline(Template::oneline(true)),
+        None => Pretty::Medium(Template::medium()),
+    };
+    // As in git, --cc implies a patch; -m alone changes nothing.
+    if opts.merge_diff == MergeDiff::Combined && !opts.format.any() {
+        opts.format.patch = true;
+    }
+    // --name-status replaces the other formats rather than adding to them.
+    if opts.format.name_status {
+        opts.format = OutputFormat { name_status: true, ..OutputFormat::default() };
+    }
+    if opts.json && opts.format.any() {
+        anyhow::bail!("--json cannot be combined with --stat, --numstat, --name-status or patches");
+    }
+    if opts.follow && opts.paths.len() != 1 {
+        anyhow::bail!("--follow requires exactly one pathspec");
+    }
+    Ok(opts)
+}
+
+/// `--format`/`--pretty` values: `oneline`, `medium`, `format:<template>`,
+/// `tformat:<template>`, or a bare template containing `%`.
+fn parse_pretty(flag: &str, value: &str) -> Result<Pretty> {
+    let template = |text: &str, terminator| Pretty::Template { template: Template::parse(text), terminator };
+    Ok(match value {
+        "oneline" => Pretty::Oneline(Template::oneline(false)),
+        "medium" => Pretty::Medium(Template::medium()),
+        "" => template("", true),
+        _ if value.starts_with("format:") => template(&value["format:".len()..], false),
+        _ if value.starts_with("tformat:") => template(&value["tformat:".len()..], true),
+        _ if value.contains('%') => template(value, true),
+        _ => anyhow::bail!("Unsupported {flag} value: {value} (expected oneline, medium or a format string)"),
+    })
+}
+
+fn count(flag: &str, value: &str) -> Result<usize> {
+    value
+        .parse()
+        .with_context(|| format!("{flag} expects a non-negative number, got {value}"))
+}
+
+fn date_limit(flag: &str, value: &str) -> Result<i64> {
+    Ok(parse_git_date(value)
+        .with_context(|| format!("Invalid date for {flag}"))?
+        .seconds)
+}
+
+fn pattern(flag: &str, value: &str) -> Result<Regex> {
+    Regex::new(value).with_context(|| format!("Invalid pattern for {flag}: {value}"))
+}
+
+/// One line of `--json` output.
+fn print_json(out: &mut dyn Write, commit: &gix::Commit, entry: &Entry, printer: &Printer) -> Result<()> {
+    let refs = printer.decorations.get(&commit.id).cloned().unwrap_or_default();
+    let info = commit_info(printer.repo, commit, &entry.parents, refs)?;
+    let report = CommitReport {
+        short: info.id.short.clone(),
+        commit: info.id.full.clone(),
+        parents: info.parents.iter().map(|id| id.full.clone()).collect(),
+        author: (&info.author).into(),
+        committer: (&info.committer).into(),
+        subject: info.subject(),
+        body: info.body().trim_end().to_string(),
+        trailers: parse_trailers(&info.message),
+        refs: info.refs.iter().map(RefReport::from).collect(),
+    };
+    writeln!(out, "{}", serde_json::to_string(&report)?)?;
+    Ok(())
 }
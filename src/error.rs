@@ -0,0 +1,30 @@
+use crate::{EXIT_CONCURRENT_UPDATE, EXIT_NOTHING_TO_COMMIT, EXIT_POLICY_REFUSAL};
+
+/// Errors that map to a specific process exit status instead of the generic `1`.
+#[derive(Debug, thiserror::Error)]
+pub enum NopeError {
+    #[error("{0}")]
+    PolicyRefusal(String),
+    #[error("Nothing to commit: the staged tree matches HEAD (use --allow-empty to override)")]
+    NothingToCommit,
+    /// The branch, or a file, no longer matches what the caller read or expected.
+    #[error("Concurrent update: {0}")]
+    ConcurrentUpdate(String),
+}
+
+impl NopeError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            NopeError::PolicyRefusal(_) => EXIT_POLICY_REFUSAL,
+            NopeError::NothingToCommit => EXIT_NOTHING_TO_COMMIT,
+            NopeError::ConcurrentUpdate(_) => EXIT_CONCURRENT_UPDATE,
+        }
+    }
+}
+
+/// Exit status for an error returned by an applet.
+pub fn exit_code_for(err: &anyhow::Error) -> i32 {
+    err.downcast_ref::<NopeError>()
+        .map(NopeError::exit_code)
+        .unwrap_or(1)
+}
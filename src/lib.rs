@@ -1,8 +1,11 @@
 pub mod applets;
+pub mod error;
 pub mod util;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const EXIT_POLICY_REFUSAL: i32 = 42;
+pub const EXIT_NOTHING_TO_COMMIT: i32 = 3;
+pub const EXIT_CONCURRENT_UPDATE: i32 = 4;
 pub const SENTINEL: &str = "Nope";
 pub const REFUSAL_STDOUT: &str = "Nope, use GitAdd, GitAddAll, GitAddDot, GitRm, GitCommit, GitAudit, GitChanges.";
 pub const APPLETS: &[&str] = &[
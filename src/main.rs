@@ -8,13 +8,13 @@ use std::process;
 fn main() {
     if let Err(err) = run() {
         eprintln!("{err}");
-        process::exit(1);
+        process::exit(git_nope::error::exit_code_for(&err));
     }
 }
 
 fn run() -> Result<()> {
     let args: Vec<String> = env::args().collect();
-    let program_name = get_program_name(args.get(0));
+    let program_name = get_program_name(args.first());
 
     match program_name.as_deref() {
         Some("GitAdd") | Some("git-nope-git-add") => git_nope::applets::git_add::run(&args),
@@ -55,7 +55,7 @@ fn handle_git_invocation(args: &[String]) -> Result<()> {
             Ok(())
         }
         _ => {
-            policy_refusal(args.get(0), "Direct git usage is blocked.");
+            policy_refusal(args.first(), "Direct git usage is blocked.");
             Ok(())
         }
     }
@@ -66,7 +66,7 @@ fn handle_unknown(program: &str, args: &[String]) -> Result<()> {
         "Unknown invocation '{program}'. Expected git nope or one of {}.",
         APPLETS.join(", ")
     );
-    policy_refusal(args.get(0), &detail);
+    policy_refusal(args.first(), &detail);
     Ok(())
 }
 
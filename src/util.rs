@@ -1,2 +1,22 @@
+pub mod budget;
+pub mod cache_tree;
 pub mod color;
+pub mod date;
+pub mod decoration;
+pub mod diffstat;
 pub mod git;
+pub mod graph;
+pub mod history;
+pub mod hooks;
+pub mod identity;
+pub mod journal;
+pub mod lint;
+pub mod message;
+pub mod pager;
+pub mod patch;
+pub mod policy;
+pub mod pretty;
+pub mod secrets;
+pub mod signing;
+pub mod trailers;
+pub mod walk;
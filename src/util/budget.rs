@@ -0,0 +1,135 @@
+use gix::bstr::ByteSlice;
+use gix::glob::wildmatch::Mode;
+
+use crate::util::diffstat::FileStat;
+
+/// How many of the largest files a budget refusal lists.
+const LARGEST_SHOWN: usize = 10;
+
+/// Change-size limits for a single commit. Unset limits are not enforced.
+///
+/// Paths matching an `exempt` pattern are left out of the main budget and
+/// checked against `exempt_max_lines` instead, so a regenerated lockfile does
+/// not crowd out the code change that came with it.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetPolicy {
+    pub max_files: Option<usize>,
+    pub max_insertions: Option<usize>,
+    pub max_deletions: Option<usize>,
+    /// Insertions plus deletions.
+    pub max_lines: Option<usize>,
+    /// Gitignore-style patterns: without a `/` they match the file name at any
+    /// depth, otherwise the full path, where `**` crosses directories.
+    pub exempt: Vec<String>,
+    pub exempt_max_lines: Option<usize>,
+}
+
+impl BudgetPolicy {
+    /// Whether any limit is set; without one there is nothing to diff for.
+    pub fn has_limits(&self) -> bool {
+        [
+            self.max_files,
+            self.max_insertions,
+            self.max_deletions,
+            self.max_lines,
+            self.exempt_max_lines,
+        ]
+        .iter()
+        .any(Option::is_some)
+    }
+
+    pub fn is_exempt(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.exempt.iter().any(|pattern| {
+            let pattern = pattern.trim_start_matches('/');
+            let value = if pattern.contains('/') { path } else { name };
+            gix::glob::wildmatch(
+                pattern.as_bytes().as_bstr(),
+                value.as_bytes().as_bstr(),
+                Mode::NO_MATCH_SLASH_LITERAL,
+            )
+        })
+    }
+}
+
+/// Totals over a set of changed files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl Totals {
+    fn add(&mut self, stat: &FileStat) {
+        self.files += 1;
+        self.insertions += stat.insertions;
+        self.deletions += stat.deletions;
+    }
+
+    pub fn lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+}
+
+/// Check `stats` against `policy`, returning the refusal report if any limit is exceeded.
+pub fn check_budget(stats: &[FileStat], policy: &BudgetPolicy) -> Option<String> {
+    let mut counted = Totals::default();
+    let mut exempt = Totals::default();
+    for stat in stats {
+        if policy.is_exempt(&stat.path) {
+            exempt.add(stat);
+        } else {
+            counted.add(stat);
+        }
+    }
+
+    let mut exceeded = Vec::new();
+    let mut limit = |value: usize, max: Option<usize>, what: &str, key: &str| {
+        if let Some(max) = max.filter(|&max| value > max) {
+            exceeded.push(format!("{value} {what} (nope.budget.{key} = {max})"));
+        }
+    };
+    limit(counted.files, policy.max_files, "files changed", "maxFiles");
+    limit(counted.insertions, policy.max_insertions, "insertions", "maxInsertions");
+    limit(counted.deletions, policy.max_deletions, "deletions", "maxDeletions");
+    limit(counted.lines(), policy.max_lines, "lines changed", "maxLines");
+    limit(exempt.lines(), policy.exempt_max_lines, "lines changed in exempt paths", "exemptMaxLines");
+    if exceeded.is_empty() {
+        return None;
+    }
+
+    let mut report = String::from("Commit blocked: staged changes exceed the change-size budget:");
+    for line in &exceeded {
+        report.push_str("\n  ");
+        report.push_str(line);
+    }
+    report.push_str(&format!(
+        "\nTotal: {} files, +{} -{}",
+        counted.files, counted.insertions, counted.deletions
+    ));
+    if exempt.files > 0 {
+        report.push_str(&format!(
+            " (exempt paths: {} files, +{} -{})",
+            exempt.files, exempt.insertions, exempt.deletions
+        ));
+    }
+
+    let mut largest: Vec<&FileStat> = stats.iter().collect();
+    largest.sort_by(|a, b| b.lines().cmp(&a.lines()).then_with(|| a.path.cmp(&b.path)));
+    report.push_str("\nLargest changes:");
+    for stat in largest.iter().take(LARGEST_SHOWN) {
+        let size = if stat.binary {
+            "binary".to_string()
+        } else {
+            format!("+{} -{}", stat.insertions, stat.deletions)
+        };
+        let tag = if policy.is_exempt(&stat.path) { " (exempt)" } else { "" };
+        report.push_str(&format!("\n  {size:>14}  {}{tag}", stat.path));
+    }
+    if largest.len() > LARGEST_SHOWN {
+        report.push_str(&format!("\n  ... and {} more", largest.len() - LARGEST_SHOWN));
+    }
+    report.push_str("\nSplit the change into smaller commits or unstage unintended paths.");
+    Some(report)
+}
@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use gix::bstr::{BStr, ByteSlice};
+use gix::index::entry::{Flags, Mode, Stage};
+use gix::index::extension::Tree;
+use gix::objs::tree::EntryKind;
+use gix::ObjectId;
+
+/// The tree written for the index, and how much of it had to be hashed.
+pub struct IndexTree {
+    pub id: ObjectId,
+    /// Directories hashed and written, rather than taken from the cache-tree.
+    pub rebuilt: usize,
+    /// Whether the refreshed cache-tree was saved to the index file.
+    pub saved: bool,
+}
+
+/// Write the index as a tree, as `git write-tree` does.
+///
+/// Directories the index's cache-tree (TREE extension) still marks as valid
+/// are taken as-is, so only those touched since the last tree write are
+/// hashed. The refreshed cache-tree is then spliced into the index file, with
+/// every other extension (split index, untracked cache, fsmonitor, sparse
+/// directories) kept byte for byte, so the next commit starts warm.
+pub fn write_index_tree(repo: &gix::Repository) -> Result<IndexTree> {
+    let index = repo.open_index().context("Failed to open index (nothing staged?)")?;
+    let mut entries = Vec::with_capacity(index.entries().len());
+    for entry in index.entries() {
+        if entry.stage() != Stage::Unconflicted {
+            anyhow::bail!("Cannot commit with unmerged paths present");
+        }
+        // Removed entries of a split index. `git add -N` placeholders stay:
+        // git counts them, though they are left out of the tree.
+        if !entry.flags.contains(Flags::REMOVE) {
+            entries.push((entry.path(&index), entry));
+        }
+    }
+
+    let mut writer = Writer { repo, rebuilt: 0 };
+    let cached = index.tree().filter(|tree| tree.name.is_empty());
+    let tree = writer.write_dir(&entries, 0, b"", cached)?;
+    let id = tree.id;
+    let saved = writer.rebuilt > 0 && save(&index, &tree)?;
+    Ok(IndexTree { id, rebuilt: writer.rebuilt, saved })
+}
+
+struct Writer<'r> {
+    repo: &'r gix::Repository,
+    rebuilt: usize,
+}
+
+impl Writer<'_> {
+    /// The cache-tree node for `entries`, all inside the directory `name`
+    /// whose paths start after `prefix_len` bytes.
+    fn write_dir(
+        &mut self,
+        entries: &[(&BStr, &gix::index::Entry)],
+        prefix_len: usize,
+        name: &[u8],
+        cached: Option<&Tree>,
+    ) -> Result<Tree> {
+        // git invalidates a directory whenever an entry below it changes; the
+        // count catches writers that forgot to.
+        if let Some(cached) = cached.filter(|tree| tree.num_entries == Some(entries.len() as u32)) {
+            return Ok(cached.clone());
+        }
+
+        let mut tree = gix::objs::Tree::empty();
+        let mut children = Vec::new();
+        // As in git, a directory holding `git add -N` entries, directly or
+        // below, is saved as invalid so no reader trusts its count.
+        let mut intent_to_add = false;
+        let mut at = 0;
+        while at < entries.len() {
+            let (path, entry) = entries[at];
+            let rest = &path[prefix_len..];
+            let slash = rest.find_byte(b'/');
+            if slash.is_none() && entry.flags.contains(Flags::INTENT_TO_ADD) {
+                intent_to_add = true;
+                at += 1;
+                continue;
+            }
+            // A sparse index stores a whole directory as one `dir/` entry.
+            if entry.mode == Mode::DIR || slash.is_none() {
+                let filename = rest.strip_suffix(b"/").unwrap_or(rest);
+                let kind = match entry.mode {
+                    Mode::FILE => EntryKind::Blob,
+                    Mode::FILE_EXECUTABLE => EntryKind::BlobExecutable,
+                    Mode::SYMLINK => EntryKind::Link,
+                    Mode::COMMIT => EntryKind::Commit,
+                    Mode::DIR => EntryKind::Tree,
+                    _ => anyhow::bail!("Unsupported mode for {path} in the index"),
+                };
+                tree.entries.push(entry_for(kind, filename, entry.id));
+                at += 1;
+                continue;
+            }
+            let dir = &rest[..=slash.expect("checked above")];
+            let end = at + entries[at..]
+                .iter()
+                .position(|(path, _)| !path[prefix_len..].starts_with(dir))
+                .unwrap_or(entries.len() - at);
+            let dir = &dir[..dir.len() - 1];
+            let child_cached = cached.and_then(|cached| {
+                let pos = cached.children.binary_search_by(|child| child.name.as_slice().cmp(dir)).ok()?;
+                Some(&cached.children[pos])
+            });
+            let child = self.write_dir(&entries[at..end], prefix_len + dir.len() + 1, dir, child_cached)?;
+            if child.num_entries.is_none() {
+                intent_to_add = true;
+            }
+            // A directory of nothing but `git add -N` entries has no tree.
+            if child.num_entries.is_some() || !child.id.is_empty_tree() {
+                tree.entries.push(entry_for(EntryKind::Tree, dir, child.id));
+            }
+            children.push(child);
+            at = end;
+        }
+        tree.entries.sort();
+
+        self.rebuilt += 1;
+        let id = self.repo.write_object(&tree).context("Failed to write tree")?.detach();
+        let num_entries = (!intent_to_add).then_some(entries.len() as u32);
+        Ok(Tree { name: name.into(), id, num_entries, children })
+    }
+}
+
+fn entry_for(kind: EntryKind, filename: &[u8], id: ObjectId) -> gix::objs::tree::Entry {
+    gix::objs::tree::Entry { mode: kind.into(), filename: filename.into(), oid: id }
+}
+
+/// Replace the TREE extension of the index file `index` was read from.
+///
+/// `false`, leaving the file alone, when it changed since, is locked by
+/// someone else, or uses a layout this does not rewrite (SHA-256, version 4
+/// path compression).
+fn save(index: &gix::index::File, tree: &Tree) -> Result<bool> {
+    let path = index.path();
+    let Ok(mut lock) = gix::lock::File::acquire_to_update_resource(path, gix::lock::acquire::Fail::Immediately, None) else {
+        return Ok(false);
+    };
+    let data = std::fs::read(path).context("Failed to read index")?;
+    let mtime = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .context("Failed to read index")?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let unchanged = data.len() > HASH_LEN && Some(&data[data.len() - HASH_LEN..]) == index.checksum().as_ref().map(|id| id.as_bytes());
+    if !unchanged || index.object_hash() != gix::hash::Kind::Sha1 {
+        return Ok(false);
+    }
+    let Some(mut layout) = Layout::parse(&data) else {
+        return Ok(false);
+    };
+
+    // Rewriting the index moves its mtime past entries written in the same
+    // second as the old one. git would then trust their stat data, so, as git
+    // does, clear their size to force a content check. Entries kept in a
+    // split index's shared file cannot be touched.
+    let racy = |secs: u32| u64::from(secs) >= mtime;
+    if layout.split && index.entries().iter().any(|entry| racy(entry.stat.mtime.secs)) {
+        return Ok(false);
+    }
+    let mut out = data[..layout.entries_end].to_vec();
+    for &offset in &layout.entries {
+        if racy(u32::from_be_bytes(out[offset + 8..offset + 12].try_into().expect("4 bytes"))) {
+            out[offset + 36..offset + 40].fill(0);
+        }
+    }
+
+    let mut body = Vec::new();
+    tree.write_to(&mut body)?;
+    let body = &body[8..];
+    // In place of the old TREE, or where git puts it: after the offset table
+    // and the split index link.
+    match layout.extensions.iter().position(|(signature, _)| signature == b"TREE") {
+        Some(at) => layout.extensions[at].1 = body,
+        None => {
+            let at = layout
+                .extensions
+                .iter()
+                .take_while(|(signature, _)| signature == b"IEOT" || signature == b"link")
+                .count();
+            layout.extensions.insert(at, (*b"TREE", body));
+        }
+    }
+    let mut written = Vec::new();
+    for &(signature, body) in &layout.extensions {
+        out.extend_from_slice(&signature);
+        out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        out.extend_from_slice(body);
+        written.push((signature, body.len() as u32));
+    }
+    if layout.end_of_index {
+        gix::index::extension::end_of_index_entry::write_to(
+            &mut out,
+            gix::hash::Kind::Sha1,
+            layout.entries_end as u32,
+            written,
+        )?;
+    }
+    let mut hasher = gix::features::hash::hasher(gix::hash::Kind::Sha1);
+    hasher.update(&out);
+    out.extend_from_slice(&hasher.digest());
+
+    use std::io::Write;
+    lock.write_all(&out).context("Failed to write index")?;
+    lock.commit().map_err(|err| err.error).context("Failed to write index")?;
+    Ok(true)
+}
+
+const HASH_LEN: usize = 20;
+
+/// Where things are in a version 2 or 3 index file.
+struct Layout<'a> {
+    /// Offset of each entry.
+    entries: Vec<usize>,
+    /// Offset just past the entries, where the extensions start.
+    entries_end: usize,
+    /// Extensions other than EOIE, in file order.
+    extensions: Vec<([u8; 4], &'a [u8])>,
+    /// Whether the file had an EOIE (end of index entry) extension.
+    end_of_index: bool,
+    /// Whether this is the split part of a split index (`link` extension).
+    split: bool,
+}
+
+impl<'a> Layout<'a> {
+    fn parse(data: &'a [u8]) -> Option<Self> {
+        let u32_at = |offset: usize| Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?));
+        if data.get(..4)? != b"DIRC" || !matches!(u32_at(4)?, 2 | 3) {
+            return None;
+        }
+        let count = u32_at(8)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        let mut offset = 12;
+        for _ in 0..count {
+            entries.push(offset);
+            let flags = u16::from_be_bytes(data.get(offset + 60..offset + 62)?.try_into().ok()?);
+            // Extended flags follow when bit 14 is set.
+            let name = offset + if flags & 0x4000 != 0 { 64 } else { 62 };
+            let len = match usize::from(flags & 0xfff) {
+                0xfff => data.get(name..)?.find_byte(0)?,
+                len => len,
+            };
+            // Entries are NUL-padded to a multiple of eight bytes.
+            offset += (name - offset + len + 8) & !7;
+        }
+
+        let entries_end = offset;
+        let end = data.len().checked_sub(HASH_LEN)?;
+        let (mut extensions, mut end_of_index, mut split) = (Vec::new(), false, false);
+        while offset < end {
+            let signature: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+            let size = u32_at(offset + 4)? as usize;
+            let body = data.get(offset + 8..offset + 8 + size)?;
+            match &signature {
+                b"EOIE" => end_of_index = true,
+                _ => {
+                    split |= &signature == b"link";
+                    extensions.push((signature, body));
+                }
+            }
+            offset += 8 + size;
+        }
+        (offset == end).then_some(Self { entries, entries_end, extensions, end_of_index, split })
+    }
+}
@@ -14,14 +14,18 @@ impl ColorConfig {
         }
 
         let env_var = env::var("GIT_NOPE_COLORS").unwrap_or_else(|_| "true".to_string());
-        let enabled = match env_var.to_lowercase().as_str() {
-            "false" | "0" | "no" | "off" | "" => false,
-            _ => true,
-        };
+        let enabled = !matches!(
+            env_var.to_lowercase().as_str(),
+            "false" | "0" | "no" | "off" | ""
+        );
 
         Self { enabled }
     }
 
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
     pub fn paint<'a>(&self, style: Style, text: impl Into<Cow<'a, str>>) -> Cow<'a, str> {
         if self.enabled {
             let text = text.into();
@@ -49,7 +53,116 @@ impl ColorConfig {
         Style::new().cyan()
     }
     
+    pub fn bold_style(&self) -> Style {
+        Style::new().bold()
+    }
+
     pub fn dim_style(&self) -> Style {
          Style::new().dimmed()
     }
 }
+
+/// The escape sequence for a git color spec such as `bold red`, `reverse 214`,
+/// `#ff8800 blue` or `reset`: attributes first, then foreground and background,
+/// as git's `color_parse` writes them. `None` for a word git would reject.
+pub fn git_color_escape(spec: &str) -> Option<String> {
+    if spec.trim().eq_ignore_ascii_case("reset") {
+        return Some("\x1b[m".to_string());
+    }
+    let mut attrs: Vec<u8> = Vec::new();
+    let mut fg = None;
+    let mut bg = None;
+    for word in spec.split_whitespace() {
+        if let Some(color) = git_color(word) {
+            if fg.is_none() {
+                fg = Some(color);
+            } else if bg.is_none() {
+                bg = Some(color);
+            } else {
+                return None;
+            }
+            continue;
+        }
+        let (negate, name) = match word.strip_prefix("no-").or_else(|| word.strip_prefix("no")) {
+            Some(name) => (true, name),
+            None => (false, word),
+        };
+        let code = match name {
+            "bold" => 1,
+            "dim" => 2,
+            "italic" => 3,
+            "ul" => 4,
+            "blink" => 5,
+            "reverse" => 7,
+            "strike" => 9,
+            _ => return None,
+        };
+        let code = match (negate, code) {
+            (false, code) => code,
+            // 21 is double underline, so "nobold" turns off bold and dim alike.
+            (true, 1) => 22,
+            (true, code) => code + 20,
+        };
+        if !attrs.contains(&code) {
+            attrs.push(code);
+        }
+    }
+    attrs.sort_unstable();
+
+    let mut parts: Vec<String> = attrs.iter().map(u8::to_string).collect();
+    let mut push_color = |color: Option<GitColor>, base: u8| match color {
+        Some(GitColor::Ansi(n)) => parts.push((n + base).to_string()),
+        Some(GitColor::Bright(n)) => parts.push((n + base + 60).to_string()),
+        Some(GitColor::Default) => parts.push((base + 9).to_string()),
+        Some(GitColor::Indexed(n)) => parts.push(format!("{};5;{n}", base + 8)),
+        Some(GitColor::Rgb(r, g, b)) => parts.push(format!("{};2;{r};{g};{b}", base + 8)),
+        Some(GitColor::Normal) | None => {}
+    };
+    push_color(fg, 30);
+    push_color(bg, 40);
+    if parts.is_empty() {
+        return Some(String::new());
+    }
+    Some(format!("\x1b[{}m", parts.join(";")))
+}
+
+#[derive(Clone, Copy)]
+enum GitColor {
+    Normal,
+    Default,
+    /// One of the eight ANSI colors, `0` for black.
+    Ansi(u8),
+    Bright(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+fn git_color(word: &str) -> Option<GitColor> {
+    const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let word = word.to_ascii_lowercase();
+    match word.as_str() {
+        "normal" => return Some(GitColor::Normal),
+        "default" => return Some(GitColor::Default),
+        _ => {}
+    }
+    if let Some(n) = NAMES.iter().position(|name| *name == word) {
+        return Some(GitColor::Ansi(n as u8));
+    }
+    if let Some(n) = word.strip_prefix("bright").and_then(|rest| NAMES.iter().position(|name| *name == rest)) {
+        return Some(GitColor::Bright(n as u8));
+    }
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+        return Some(GitColor::Rgb(channel(0)?, channel(2)?, channel(4)?));
+    }
+    match word.parse::<i32>().ok()? {
+        -1 => Some(GitColor::Normal),
+        n @ 0..=7 => Some(GitColor::Ansi(n as u8)),
+        n @ 8..=15 => Some(GitColor::Bright(n as u8 - 8)),
+        n @ 16..=255 => Some(GitColor::Indexed(n as u8)),
+        _ => None,
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Offset, TimeZone};
+use gix::date::Time;
+
+/// Parse a date in any format git accepts for `GIT_AUTHOR_DATE` and friends:
+/// git's internal `@<epoch> [<zone>]` and `<epoch> <zone>`, RFC 2822, ISO 8601
+/// (with `T` or space, zone `Z`, `+hh:mm` or `+hhmm`, or no zone meaning local
+/// time), git's default log format, and relative dates such as `2 weeks ago`.
+pub fn parse_git_date(input: &str) -> Result<Time> {
+    let input = input.trim();
+
+    if let Some(raw) = input.strip_prefix('@') {
+        let mut parts = raw.split_whitespace();
+        let seconds: i64 = parts
+            .next()
+            .and_then(|s| s.parse().ok())
+            .with_context(|| format!("Invalid date: {input}"))?;
+        return match parts.next() {
+            Some(zone) => gix::date::parse(&format!("{seconds} {zone}"), None)
+                .with_context(|| format!("Invalid date: {input}")),
+            None => Ok(Time::new(seconds, 0)),
+        };
+    }
+
+    if let Some(time) = parse_iso_local_or_zulu(input) {
+        return Ok(time);
+    }
+
+    gix::date::parse(input, Some(std::time::SystemTime::now()))
+        .with_context(|| format!("Invalid date: {input}"))
+}
+
+/// ISO 8601 forms gix does not cover: a trailing `Z`, and no zone at all.
+fn parse_iso_local_or_zulu(input: &str) -> Option<Time> {
+    let (naive_part, utc) = match input.strip_suffix('Z') {
+        Some(rest) => (rest, true),
+        None => (input, false),
+    };
+    let naive = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(naive_part, fmt).ok())?;
+
+    if utc {
+        return Some(Time::new(naive.and_utc().timestamp(), 0));
+    }
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(Time::new(local.timestamp(), local.offset().fix().local_minus_utc()))
+}
+
+/// The time for a new signature: `env_var` if set, else `pinned`, else now.
+pub fn signature_time(env_var: &str, pinned: Option<&str>) -> Result<Time> {
+    if let Ok(value) = std::env::var(env_var) {
+        if !value.trim().is_empty() {
+            return parse_git_date(&value).with_context(|| format!("Invalid {env_var}"));
+        }
+    }
+    match pinned {
+        Some(pinned) => parse_git_date(pinned).context("Invalid nope.commit.pinnedDate"),
+        None => Ok(Time::now_local_or_utc()),
+    }
+}
+
+/// `+hhmm` / `-hhmm`, as in raw commit headers.
+pub fn format_offset(offset_seconds: i32) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let minutes = offset_seconds.unsigned_abs() / 60;
+    format!("{sign}{:02}{:02}", minutes / 60, minutes % 60)
+}
+
+/// How log dates are shown, as git's `--date=<mode>` and `log.date`. Every
+/// mode but `relative` and `unix` shows the time in the signature's own
+/// timezone, as git does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DateMode {
+    /// `Thu Apr 7 22:13:13 2005 +0200`.
+    #[default]
+    Default,
+    /// `2005-04-07 22:13:13 +0200`.
+    Iso,
+    /// `2005-04-07T22:13:13+02:00`.
+    IsoStrict,
+    /// `Thu, 7 Apr 2005 22:13:13 +0200`.
+    Rfc,
+    /// `2005-04-07`.
+    Short,
+    /// `3 weeks ago`.
+    Relative,
+    /// `1112904793`.
+    Unix,
+    /// `1112904793 +0200`, as in raw commit headers.
+    Raw,
+    /// `format:<strftime>`.
+    Format(String),
+}
+
+impl DateMode {
+    /// A `--date` value. git's long names (`iso8601`, `rfc2822`) are accepted
+    /// too.
+    pub fn parse(value: &str) -> Result<Self> {
+        Ok(match value {
+            "default" => Self::Default,
+            "iso" | "iso8601" => Self::Iso,
+            "iso-strict" | "iso8601-strict" => Self::IsoStrict,
+            "rfc" | "rfc2822" => Self::Rfc,
+            "short" => Self::Short,
+            "relative" => Self::Relative,
+            "unix" => Self::Unix,
+            "raw" => Self::Raw,
+            _ => {
+                let format = value
+                    .strip_prefix("format:")
+                    .with_context(|| format!("Unknown date format: {value}"))?;
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    anyhow::bail!("Invalid strftime format: {format}");
+                }
+                Self::Format(format.to_string())
+            }
+        })
+    }
+
+    pub fn format(&self, time: &Time) -> String {
+        let pattern = match self {
+            Self::Default => "%a %b %-d %H:%M:%S %Y %z",
+            Self::Iso => "%Y-%m-%d %H:%M:%S %z",
+            Self::Rfc => "%a, %-d %b %Y %H:%M:%S %z",
+            Self::Short => "%Y-%m-%d",
+            Self::Format(format) => format,
+            Self::IsoStrict => return format_iso_strict(time),
+            Self::Relative => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs() as i64);
+                return format_relative(time, now);
+            }
+            Self::Unix => return time.seconds.to_string(),
+            Self::Raw => return format!("{} {}", time.seconds, format_offset(time.offset)),
+        };
+        match in_own_zone(time) {
+            Some(dt) => dt.format(pattern).to_string(),
+            None => time.seconds.to_string(),
+        }
+    }
+}
+
+/// `time` in the timezone it was recorded in.
+fn in_own_zone(time: &Time) -> Option<DateTime<FixedOffset>> {
+    FixedOffset::east_opt(time.offset)?.timestamp_opt(time.seconds, 0).single()
+}
+
+/// ISO 8601 with the signature's own offset, e.g. `2005-04-07T22:13:13+02:00`.
+pub fn format_iso_strict(time: &Time) -> String {
+    in_own_zone(time)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| time.seconds.to_string())
+}
+
+/// How long before `now` (Unix seconds) `time` was, as git's relative dates
+/// put it: `5 minutes ago`, `3 weeks ago`, `1 year, 2 months ago`.
+pub fn format_relative(time: &Time, now: i64) -> String {
+    let Ok(diff) = u64::try_from(now - time.seconds) else {
+        return "in the future".to_string();
+    };
+    let ago = |n: u64, unit: &str| format!("{n} {unit}{} ago", if n == 1 { "" } else { "s" });
+    if diff < 90 {
+        return ago(diff, "second");
+    }
+    let minutes = (diff + 30) / 60;
+    if minutes < 90 {
+        return ago(minutes, "minute");
+    }
+    let hours = (minutes + 30) / 60;
+    if hours < 36 {
+        return ago(hours, "hour");
+    }
+    let days = (hours + 12) / 24;
+    if days < 14 {
+        return ago(days, "day");
+    }
+    if days < 70 {
+        return ago((days + 3) / 7, "week");
+    }
+    if days < 365 {
+        return ago((days + 15) / 30, "month");
+    }
+    if days < 1825 {
+        let total_months = (days * 12 * 2 + 365) / (365 * 2);
+        let (years, months) = (total_months / 12, total_months % 12);
+        let years_part = format!("{years} year{}", if years == 1 { "" } else { "s" });
+        if months == 0 {
+            return format!("{years_part} ago");
+        }
+        return format!("{years_part}, {}", ago(months, "month"));
+    }
+    ago((days + 183) / 365, "year")
+}
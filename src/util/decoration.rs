@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use gix::ObjectId;
+use std::collections::HashMap;
+
+use crate::util::color::git_color_escape;
+
+/// A ref shown next to the commit it points at, as `git log --decorate` shows
+/// refs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoration {
+    /// `HEAD`, with the branch it is attached to unless detached.
+    Head { branch: Option<String> },
+    /// A local branch, `refs/heads/<name>`.
+    Branch(String),
+    /// A remote-tracking branch such as `origin/main`, symbolic ones like
+    /// `origin/HEAD` included.
+    Remote(String),
+    /// A tag, `refs/tags/<name>`; annotated tags decorate the commit they
+    /// point at.
+    Tag(String),
+    /// `refs/stash`.
+    Stash,
+}
+
+impl Decoration {
+    /// The ref's name as git shows it, without the `tag: ` prefix.
+    pub fn name(&self) -> &str {
+        match self {
+            Decoration::Head { .. } => "HEAD",
+            Decoration::Branch(name) | Decoration::Remote(name) | Decoration::Tag(name) => name,
+            Decoration::Stash => "refs/stash",
+        }
+    }
+
+    /// git's default `color.decorate.<slot>`.
+    fn color(&self) -> &'static str {
+        match self {
+            Decoration::Head { .. } => "bold cyan",
+            Decoration::Branch(_) => "bold green",
+            Decoration::Remote(_) => "bold red",
+            Decoration::Tag(_) => "bold yellow",
+            Decoration::Stash => "bold magenta",
+        }
+    }
+}
+
+/// Every ref git decorates with by default, keyed by the commit it resolves
+/// to after following symbolic refs and peeling tags.
+///
+/// Each commit's list is in git's order: `HEAD` first, then the other refs in
+/// reverse order of their full names, so tags come before remote-tracking
+/// branches and those before local branches.
+pub fn load(repo: &gix::Repository) -> Result<HashMap<ObjectId, Vec<Decoration>>> {
+    let mut refs = Vec::new();
+    let references = repo.references().context("Failed to list refs")?;
+    for reference in references.all().context("Failed to list refs")? {
+        // Like git, skip refs that cannot be read or resolved.
+        let Ok(mut reference) = reference else { continue };
+        let full = reference.name().as_bstr().to_string();
+        let decoration = if let Some(name) = full.strip_prefix("refs/heads/") {
+            Decoration::Branch(name.to_string())
+        } else if let Some(name) = full.strip_prefix("refs/remotes/") {
+            Decoration::Remote(name.to_string())
+        } else if let Some(name) = full.strip_prefix("refs/tags/") {
+            Decoration::Tag(name.to_string())
+        } else if full == "refs/stash" {
+            Decoration::Stash
+        } else {
+            continue;
+        };
+        if let Ok(id) = reference.peel_to_id_in_place() {
+            refs.push((full, id.detach(), decoration));
+        }
+    }
+    refs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut map: HashMap<ObjectId, Vec<Decoration>> = HashMap::new();
+    for (_, id, decoration) in refs.into_iter().rev() {
+        map.entry(id).or_default().push(decoration);
+    }
+    if let Ok(head) = repo.head() {
+        if let Some(id) = head.id() {
+            let branch = head
+                .referent_name()
+                .and_then(|name| name.as_bstr().to_string().strip_prefix("refs/heads/").map(str::to_string));
+            map.entry(id.detach()).or_default().insert(0, Decoration::Head { branch });
+        }
+    }
+    Ok(map)
+}
+
+/// ` (HEAD -> main, tag: v1.0, origin/main)` as the built-in formats and `%d`
+/// show decorations, or nothing for an undecorated commit.
+pub fn parenthesized(decorations: &[Decoration], use_color: bool) -> String {
+    format(decorations, " (", ")", use_color)
+}
+
+/// `HEAD -> main, tag: v1.0, origin/main`, as `%D` shows decorations.
+pub fn list(decorations: &[Decoration], use_color: bool) -> String {
+    format(decorations, "", "", use_color)
+}
+
+/// git's `format_decorations`: an attached HEAD absorbs its branch as
+/// `HEAD -> <branch>`, each ref gets its own color and the punctuation the
+/// commit id's.
+fn format(decorations: &[Decoration], prefix: &str, suffix: &str, use_color: bool) -> String {
+    if decorations.is_empty() {
+        return String::new();
+    }
+    let color = |spec: &str| match use_color {
+        true => git_color_escape(spec).unwrap_or_default(),
+        false => String::new(),
+    };
+    let reset = color("reset");
+    let punctuation = |text: &str| format!("{}{text}{reset}", color("yellow"));
+
+    let current = decorations.iter().find_map(|decoration| match decoration {
+        Decoration::Head { branch: Some(branch) } => decorations
+            .iter()
+            .find(|other| matches!(other, Decoration::Branch(name) if name == branch)),
+        _ => None,
+    });
+
+    let mut out = punctuation(prefix);
+    let mut first = true;
+    for decoration in decorations {
+        if Some(decoration) == current {
+            continue;
+        }
+        if !first {
+            out.push_str(&punctuation(", "));
+        }
+        first = false;
+        out.push_str(&color(decoration.color()));
+        if let Decoration::Tag(_) = decoration {
+            out.push_str("tag: ");
+        }
+        out.push_str(decoration.name());
+        if let (Decoration::Head { .. }, Some(branch)) = (decoration, current) {
+            out.push_str(" -> ");
+            out.push_str(&reset);
+            out.push_str(&color(branch.color()));
+            out.push_str(branch.name());
+        }
+        out.push_str(&reset);
+    }
+    out.push_str(&punctuation(suffix));
+    out
+}
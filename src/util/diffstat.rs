@@ -0,0 +1,290 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::util::color::ColorConfig;
+use crate::util::git::open_for_tree_diffs;
+
+/// Per-file change summary between two trees. Binary files count as one
+/// changed file with no lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileStat {
+    pub path: String,
+    /// `A`, `M`, `D`, `R`, `C` or `T`, as in `git diff --name-status`.
+    pub status: char,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+    /// Source path of a rename or copy.
+    #[serde(skip)]
+    pub old_path: Option<String>,
+    /// Blob sizes in bytes, shown as `Bin <old> -> <new> bytes` for binary files.
+    #[serde(skip)]
+    pub old_size: usize,
+    #[serde(skip)]
+    pub new_size: usize,
+}
+
+impl FileStat {
+    pub fn lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+
+    /// Name as `git diff --stat` prints it: `dir/{old => new}` for renames.
+    pub fn display_name(&self) -> String {
+        match &self.old_path {
+            Some(old_path) => rename_name(old_path, &self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Diff `old_tree` against `new_tree`, with rename detection, one entry per
+/// changed path in diff order.
+pub fn diff_trees(
+    repo: &gix::Repository,
+    old_tree: gix::ObjectId,
+    new_tree: gix::ObjectId,
+) -> Result<Vec<FileStat>> {
+    let git2_repo = open_for_tree_diffs(repo)?;
+    let old_tree = git2_repo.find_tree(git2::Oid::from_bytes(old_tree.as_bytes())?)?;
+    let new_tree = git2_repo.find_tree(git2::Oid::from_bytes(new_tree.as_bytes())?)?;
+    let mut diff = git2_repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+    diff.find_similar(None)?;
+    stats_from_diff(&git2_repo, &diff)
+}
+
+/// One [`FileStat`] per delta of an already computed diff.
+pub fn stats_from_diff(repo: &git2::Repository, diff: &git2::Diff) -> Result<Vec<FileStat>> {
+    let mut stats = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().into_owned());
+        let path = path_of(delta.new_file())
+            .or_else(|| path_of(delta.old_file()))
+            .unwrap_or_default();
+        let status = match delta.status() {
+            git2::Delta::Added => 'A',
+            git2::Delta::Deleted => 'D',
+            git2::Delta::Renamed => 'R',
+            git2::Delta::Copied => 'C',
+            git2::Delta::Typechange => 'T',
+            _ => 'M',
+        };
+        let old_path = matches!(status, 'R' | 'C')
+            .then(|| path_of(delta.old_file()))
+            .flatten();
+
+        let patch = git2::Patch::from_diff(diff, idx)?;
+        // No patch means git2 treated the file as binary.
+        let (insertions, deletions, binary) = match patch {
+            Some(patch) if !patch.delta().flags().is_binary() => {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                (insertions, deletions, false)
+            }
+            _ => (0, 0, true),
+        };
+        let blob_size = |file: git2::DiffFile| -> usize {
+            if !binary || file.id().is_zero() {
+                return 0;
+            }
+            repo.find_blob(file.id()).map(|blob| blob.size()).unwrap_or(0)
+        };
+        stats.push(FileStat {
+            path,
+            status,
+            insertions,
+            deletions,
+            binary,
+            old_path,
+            old_size: blob_size(delta.old_file()),
+            new_size: blob_size(delta.new_file()),
+        });
+    }
+    Ok(stats)
+}
+
+/// `git diff --stat` lines, including the closing summary, laid out for a
+/// terminal `width` columns wide the way git scales names and bars.
+pub fn stat_lines(stats: &[FileStat], width: usize, colors: &ColorConfig) -> Vec<String> {
+    let names: Vec<String> = stats.iter().map(FileStat::display_name).collect();
+    let max_len = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let mut max_change = 0;
+    let mut bin_width = 0;
+    let mut number_width = 0;
+    for stat in stats {
+        if stat.binary {
+            // "Bin XXX -> YYY bytes"
+            bin_width = bin_width.max(14 + decimal_width(stat.new_size) + decimal_width(stat.old_size));
+            number_width = 3;
+        } else {
+            max_change = max_change.max(stat.lines());
+        }
+    }
+
+    number_width = number_width.max(decimal_width(max_change));
+    let width = width.max(16 + 6 + number_width);
+    let mut graph_width = if max_change + 4 > bin_width { max_change } else { bin_width - 4 };
+    let mut name_width = max_len;
+    if name_width + number_width + 6 + graph_width > width {
+        let graph_room = (width * 3 / 8).saturating_sub(number_width + 6);
+        if graph_width > graph_room {
+            graph_width = graph_room.max(6);
+        }
+        if name_width > width - number_width - 6 - graph_width {
+            name_width = width - number_width - 6 - graph_width;
+        } else {
+            graph_width = width - number_width - 6 - name_width;
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (stat, name) in stats.iter().zip(&names) {
+        let name = fit_name(name, name_width);
+        let padding = name_width.saturating_sub(name.chars().count());
+        let mut line = format!(" {name}{:padding$} | ", "");
+        if stat.binary {
+            line.push_str(&format!("{:>number_width$}", "Bin"));
+            if stat.old_size != 0 || stat.new_size != 0 {
+                line.push_str(&format!(
+                    " {} -> {} bytes",
+                    colors.paint(colors.red_style(), stat.old_size.to_string()),
+                    colors.paint(colors.green_style(), stat.new_size.to_string())
+                ));
+            }
+            lines.push(line);
+            continue;
+        }
+
+        let (mut add, mut del) = (stat.insertions, stat.deletions);
+        if graph_width <= max_change {
+            let mut total = scale_linear(add + del, graph_width, max_change);
+            if total < 2 && add > 0 && del > 0 {
+                total = 2;
+            }
+            if add < del {
+                add = scale_linear(add, graph_width, max_change);
+                del = total - add;
+            } else {
+                del = scale_linear(del, graph_width, max_change);
+                add = total - del;
+            }
+        }
+        line.push_str(&format!("{:>number_width$}", stat.lines()));
+        if stat.lines() > 0 {
+            line.push(' ');
+        }
+        if add > 0 {
+            line.push_str(&colors.paint(colors.green_style(), "+".repeat(add)));
+        }
+        if del > 0 {
+            line.push_str(&colors.paint(colors.red_style(), "-".repeat(del)));
+        }
+        lines.push(line);
+    }
+    lines.push(summary_line(stats));
+    lines
+}
+
+/// ` N files changed, X insertions(+), Y deletions(-)`
+pub fn summary_line(stats: &[FileStat]) -> String {
+    let files = stats.len();
+    let insertions: usize = stats.iter().filter(|s| !s.binary).map(|s| s.insertions).sum();
+    let deletions: usize = stats.iter().filter(|s| !s.binary).map(|s| s.deletions).sum();
+    if files == 0 {
+        return " 0 files changed".to_string();
+    }
+    let plural = |n: usize, one: &str, many: &str| format!("{n} {}", if n == 1 { one } else { many });
+    let mut line = format!(" {}", plural(files, "file changed", "files changed"));
+    if insertions > 0 || deletions == 0 {
+        line.push_str(&format!(", {}", plural(insertions, "insertion(+)", "insertions(+)")));
+    }
+    if deletions > 0 || insertions == 0 {
+        line.push_str(&format!(", {}", plural(deletions, "deletion(-)", "deletions(-)")));
+    }
+    line
+}
+
+/// `git diff --numstat` lines: `added<TAB>deleted<TAB>name`, `-` for binary files.
+pub fn numstat_lines(stats: &[FileStat]) -> Vec<String> {
+    stats
+        .iter()
+        .map(|stat| {
+            if stat.binary {
+                format!("-\t-\t{}", stat.display_name())
+            } else {
+                format!("{}\t{}\t{}", stat.insertions, stat.deletions, stat.display_name())
+            }
+        })
+        .collect()
+}
+
+/// `old => new` with the common leading directories and trailing components
+/// factored out, e.g. `src/{a.rs => b.rs}`.
+fn rename_name(old: &str, new: &str) -> String {
+    let (a, b) = (old.as_bytes(), new.as_bytes());
+
+    // Common prefix, ending with a slash.
+    let mut prefix = 0;
+    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+        if x != y {
+            break;
+        }
+        if *x == b'/' {
+            prefix = i + 1;
+        }
+    }
+
+    // Common suffix, starting with a slash. With a common prefix, the scan may
+    // run one byte into it to see the same slash.
+    let floor = prefix.saturating_sub(1);
+    let mut suffix = 0;
+    let (mut i, mut j) = (a.len(), b.len());
+    while i >= floor && j >= floor {
+        let (x, y) = (a.get(i), b.get(j));
+        if x != y {
+            break;
+        }
+        if x == Some(&b'/') {
+            suffix = a.len() - i;
+        }
+        if i == 0 || j == 0 {
+            break;
+        }
+        i -= 1;
+        j -= 1;
+    }
+
+    let a_mid = &old[prefix.min(old.len())..old.len().saturating_sub(suffix).max(prefix)];
+    let b_mid = &new[prefix.min(new.len())..new.len().saturating_sub(suffix).max(prefix)];
+    if prefix + suffix == 0 {
+        return format!("{a_mid} => {b_mid}");
+    }
+    format!("{}{{{a_mid} => {b_mid}}}{}", &old[..prefix], &old[old.len() - suffix..])
+}
+
+/// Shorten `name` to `width` columns as git does: `...` plus the tail,
+/// starting at a directory boundary when there is one.
+fn fit_name(name: &str, width: usize) -> String {
+    let len = name.chars().count();
+    if len <= width {
+        return name.to_string();
+    }
+    let keep = width.saturating_sub(3);
+    let tail: String = name.chars().skip(len - keep).collect();
+    match tail.find('/') {
+        Some(slash) => format!("...{}", &tail[slash..]),
+        None => format!("...{tail}"),
+    }
+}
+
+/// Scale `it` into a bar at most `width` wide, keeping at least one column for
+/// any change.
+fn scale_linear(it: usize, width: usize, max_change: usize) -> usize {
+    if it == 0 {
+        return 0;
+    }
+    1 + it * (width - 1) / max_change
+}
+
+fn decimal_width(n: usize) -> usize {
+    n.to_string().len()
+}
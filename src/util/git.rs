@@ -5,6 +5,15 @@ pub fn discover_repo() -> Result<Repository> {
     Repository::discover(".").context("Failed to discover repository")
 }
 
+/// `repo` opened with libgit2 for diffing trees. libgit2 cannot read every
+/// index git writes (split indexes, for one), and tree diffs only consult the
+/// index for `.gitattributes`, so it gets an empty in-memory one instead.
+pub fn open_for_tree_diffs(repo: &gix::Repository) -> Result<Repository> {
+    let git2_repo = Repository::open(repo.git_dir()).context("Failed to open repository")?;
+    git2_repo.set_index(&mut git2::Index::new()?)?;
+    Ok(git2_repo)
+}
+
 pub fn head_branch(repo: &Repository) -> Result<Branch<'_>> {
     let head = repo.head().context("Failed to get HEAD")?;
     if !head.is_branch() {
@@ -34,6 +43,118 @@ pub fn short_head_commit(repo: &Repository, len: usize) -> Result<String> {
     }
 }
 
+/// Normalise a user-supplied full or abbreviated object id (4 to 40 hex digits)
+/// to lowercase, for prefix comparison against a full id.
+pub fn parse_abbrev_oid(input: &str) -> Result<String> {
+    let hex = input.trim().to_ascii_lowercase();
+    if hex.len() < 4 || hex.len() > 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid object id: {input}");
+    }
+    Ok(hex)
+}
+
+/// Revision arguments as `git rev-list` takes them, resolved to commits:
+/// `<rev>`, `^<rev>`, `<rev>..<rev>` and `<rev>...<rev>`, where an empty side
+/// of a range stands for `HEAD`.
+#[derive(Default)]
+pub struct Revisions {
+    /// Commits to walk from.
+    pub include: Vec<gix::ObjectId>,
+    /// Commits whose history is left out: `^<rev>`, the left side of `A..B`,
+    /// and the merge bases of `A...B`.
+    pub exclude: Vec<gix::ObjectId>,
+    /// Left sides of `A...B`; `--left-right` marks their commits with `<`.
+    pub left: Vec<gix::ObjectId>,
+}
+
+impl Revisions {
+    /// Add one revision argument.
+    pub fn push(&mut self, repo: &gix::Repository, arg: &str) -> Result<()> {
+        let side = |rev: &str| resolve_revision(repo, if rev.is_empty() { "HEAD" } else { rev });
+        if let Some((left, right)) = arg.split_once("...") {
+            let (left, right) = (side(left)?, side(right)?);
+            let cache = repo.commit_graph_if_enabled()?;
+            let mut graph = repo.revision_graph(cache.as_ref());
+            let bases = repo
+                .merge_bases_many_with_graph(left, &[right], &mut graph)
+                .with_context(|| format!("Failed to find the merge base of {arg}"))?;
+            self.exclude.extend(bases.into_iter().map(|id| id.detach()));
+            self.include.extend([left, right]);
+            self.left.push(left);
+        } else if let Some((from, to)) = arg.split_once("..") {
+            self.exclude.push(side(from)?);
+            self.include.push(side(to)?);
+        } else if let Some(rev) = arg.strip_prefix('^') {
+            self.exclude.push(resolve_revision(repo, rev)?);
+        } else {
+            self.include.push(resolve_revision(repo, arg)?);
+        }
+        Ok(())
+    }
+
+    /// Whether no revision was given, so callers fall back to their default.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+}
+
+/// The commit a single revision names: a ref as git looks it up (`main`,
+/// `v1.0`, `origin/main`, `HEAD` or `@`) or a full or abbreviated object id,
+/// followed by any number of `^<n>` (the nth parent, the first if `n` is
+/// omitted, the commit itself for `^0`) and `~<n>` (the nth first-parent
+/// ancestor) suffixes. Tags are peeled to the commit they point at.
+pub fn resolve_revision(repo: &gix::Repository, spec: &str) -> Result<gix::ObjectId> {
+    let (name, mut suffixes) = spec.split_at(spec.find(['^', '~']).unwrap_or(spec.len()));
+    let mut commit = repo
+        .find_object(resolve_name(repo, name)?)?
+        .peel_to_commit()
+        .with_context(|| format!("{name} does not name a commit"))?;
+    while let Some(op) = suffixes.chars().next() {
+        let digits = suffixes[1..].bytes().take_while(u8::is_ascii_digit).count();
+        let count: usize = match digits {
+            0 => 1,
+            _ => suffixes[1..=digits]
+                .parse()
+                .with_context(|| format!("Invalid revision: {spec}"))?,
+        };
+        suffixes = &suffixes[1 + digits..];
+        let (parent, steps) = match op {
+            '^' if count == 0 => continue,
+            '^' => (count - 1, 1),
+            '~' => (0, count),
+            _ => anyhow::bail!("Invalid revision: {spec}"),
+        };
+        for _ in 0..steps {
+            let id = commit
+                .parent_ids()
+                .nth(parent)
+                .with_context(|| format!("Invalid revision: {spec} (no such parent)"))?;
+            commit = id.object()?.try_into_commit()?;
+        }
+    }
+    Ok(commit.id)
+}
+
+/// A ref name or object id. As in git, a name that is both a ref and an
+/// abbreviated id means the ref; a full id always means the object.
+fn resolve_name(repo: &gix::Repository, name: &str) -> Result<gix::ObjectId> {
+    let name = if name == "@" { "HEAD" } else { name };
+    if name.len() != 40 {
+        if let Ok(Some(reference)) = repo.try_find_reference(name) {
+            return Ok(reference.into_fully_peeled_id()?.detach());
+        }
+    }
+    if let Ok(hex) = parse_abbrev_oid(name) {
+        let prefix = gix::hash::Prefix::from_hex(&hex)?;
+        match repo.objects.lookup_prefix(prefix, None)? {
+            Some(Ok(id)) => return Ok(id),
+            Some(Err(())) => anyhow::bail!("Ambiguous object id: {name}"),
+            None => {}
+        }
+    }
+    anyhow::bail!("Unknown revision: {name}")
+}
+
 pub fn upstream_remote_url(repo: &Repository, branch: &Branch) -> Result<Option<String>> {
     let upstream = match branch.upstream() {
         Ok(b) => b,
@@ -0,0 +1,705 @@
+//! ASCII history graph, ported from git's `graph.c`.
+//!
+//! The renderer is fed commits in display order (children before parents)
+//! together with their *interesting* parents, i.e. the parents that will also be
+//! shown. Each commit produces zero or more lines before its commit line (to make
+//! room for octopus merges), the commit line itself, and zero or more lines after
+//! it while merge edges and collapsing branch lines are drawn. Text printed to the
+//! right of the graph is interleaved by the caller, see [`Graph::render_commit`].
+
+use owo_colors::Style;
+
+use crate::util::color::ColorConfig;
+
+/// Lane colors, in git's default `color.diff.graph` order.
+fn column_styles() -> [Style; 12] {
+    [
+        Style::new().red(),
+        Style::new().green(),
+        Style::new().yellow(),
+        Style::new().blue(),
+        Style::new().magenta(),
+        Style::new().cyan(),
+        Style::new().bold().red(),
+        Style::new().bold().green(),
+        Style::new().bold().yellow(),
+        Style::new().bold().blue(),
+        Style::new().bold().magenta(),
+        Style::new().bold().cyan(),
+    ]
+}
+const COLUMN_COLORS: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Padding,
+    Skip,
+    PreCommit,
+    Commit,
+    PostMerge,
+    Collapsing,
+}
+
+#[derive(Debug, Clone)]
+struct Column<Id> {
+    commit: Id,
+    /// Index into [`column_styles`], or [`COLUMN_COLORS`] for no color.
+    color: usize,
+}
+
+/// One rendered graph row and its display width.
+struct Line<'a> {
+    buf: String,
+    width: usize,
+    colors: &'a ColorConfig,
+}
+
+impl Line<'_> {
+    fn push(&mut self, c: char) {
+        self.buf.push(c);
+        self.width += 1;
+    }
+
+    fn push_n(&mut self, c: char, n: usize) {
+        for _ in 0..n {
+            self.push(c);
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.buf.push_str(s);
+        self.width += s.chars().count();
+    }
+
+    fn write_column<Id>(&mut self, column: &Column<Id>, c: char) {
+        if column.color < COLUMN_COLORS {
+            let painted = self.colors.paint(column_styles()[column.color], c.to_string());
+            self.buf.push_str(&painted);
+            self.width += 1;
+        } else {
+            self.push(c);
+        }
+    }
+}
+
+const MERGE_CHARS: [char; 3] = ['/', '|', '\\'];
+
+/// Lane allocation state carried from one commit to the next.
+pub struct Graph<Id> {
+    colors: ColorConfig,
+    commit: Option<Id>,
+    parents: Vec<Id>,
+    /// Commit marker: `*`, or `<`/`>` for `--left-right`.
+    mark: String,
+    width: usize,
+    expansion_row: usize,
+    state: State,
+    prev_state: State,
+    commit_index: usize,
+    prev_commit_index: usize,
+    /// Where a merge's first parent sits: 0 = in a column to its left, 1 = below
+    /// it, -1 = not yet placed.
+    merge_layout: isize,
+    edges_added: isize,
+    prev_edges_added: isize,
+    columns: Vec<Column<Id>>,
+    new_columns: Vec<Column<Id>>,
+    mapping: Vec<Option<usize>>,
+    old_mapping: Vec<Option<usize>>,
+    mapping_size: usize,
+    default_column_color: usize,
+}
+
+impl<Id: Clone + PartialEq> Graph<Id> {
+    pub fn new(colors: ColorConfig) -> Self {
+        Self {
+            colors,
+            commit: None,
+            parents: Vec::new(),
+            mark: "*".to_string(),
+            width: 0,
+            expansion_row: 0,
+            state: State::Padding,
+            prev_state: State::Padding,
+            commit_index: 0,
+            prev_commit_index: 0,
+            merge_layout: 0,
+            edges_added: 0,
+            prev_edges_added: 0,
+            columns: Vec::new(),
+            new_columns: Vec::new(),
+            mapping: Vec::new(),
+            old_mapping: Vec::new(),
+            mapping_size: 0,
+            default_column_color: COLUMN_COLORS - 1,
+        }
+    }
+
+    /// Advance to `commit`. `parents` must hold only the parents that will be shown.
+    pub fn update(&mut self, commit: Id, parents: Vec<Id>, mark: &str) {
+        self.commit = Some(commit);
+        self.parents = parents;
+        self.mark = mark.to_string();
+        self.prev_commit_index = self.commit_index;
+        self.update_columns();
+        self.expansion_row = 0;
+
+        // If the previous commit never reached the padding state its output was
+        // cut short; mark the gap. Octopus merges with branch lines to their right
+        // need expansion rows before the commit line.
+        self.state = if self.state != State::Padding {
+            State::Skip
+        } else if self.needs_pre_commit_line() {
+            State::PreCommit
+        } else {
+            State::Commit
+        };
+    }
+
+    /// Whether every line for the current commit has been produced.
+    pub fn is_commit_finished(&self) -> bool {
+        self.state == State::Padding
+    }
+
+    /// The next graph row and whether it was the commit row.
+    pub fn next_line(&mut self) -> (String, bool) {
+        let colors = self.colors;
+        let mut line = Line {
+            buf: String::new(),
+            width: 0,
+            colors: &colors,
+        };
+        if self.commit.is_none() {
+            return (String::new(), false);
+        }
+        let mut shown_commit_line = false;
+        match self.state {
+            State::Padding => self.output_padding_line(&mut line),
+            State::Skip => self.output_skip_line(&mut line),
+            State::PreCommit => self.output_pre_commit_line(&mut line),
+            State::Commit => {
+                self.output_commit_line(&mut line);
+                shown_commit_line = true;
+            }
+            State::PostMerge => self.output_post_merge_line(&mut line),
+            State::Collapsing => self.output_collapsing_line(&mut line),
+        }
+        self.pad_horizontally(&mut line);
+        (line.buf, shown_commit_line)
+    }
+
+    /// A row that continues every branch line unchanged, for text between commits.
+    pub fn padding_line(&mut self) -> String {
+        if self.state != State::Commit {
+            return self.next_line().0;
+        }
+        let colors = self.colors;
+        let mut line = Line {
+            buf: String::new(),
+            width: 0,
+            colors: &colors,
+        };
+        for column in &self.columns {
+            line.write_column(column, '|');
+            if Some(&column.commit) == self.commit.as_ref() && self.parents.len() > 2 {
+                line.push_n(' ', (self.parents.len() - 2) * 2);
+            } else {
+                line.push(' ');
+            }
+        }
+        self.pad_horizontally(&mut line);
+        self.prev_state = State::Padding;
+        line.buf
+    }
+
+    /// Prefix each line of `text` with the graph, the way `git log --graph` does:
+    /// rows before the commit row stand alone, the commit row carries the first
+    /// line, and rows still owed afterwards (merge edges, collapsing lanes) follow
+    /// the text. A trailing newline in `text` is kept.
+    pub fn render_commit(&mut self, text: &str) -> String {
+        let mut out = String::new();
+
+        // graph_show_commit
+        let mut shown_commit_line = false;
+        if self.is_commit_finished() {
+            out.push_str(&self.padding_line());
+            shown_commit_line = true;
+        }
+        while !shown_commit_line && !self.is_commit_finished() {
+            let (row, is_commit) = self.next_line();
+            out.push_str(&row);
+            shown_commit_line = is_commit;
+            if !shown_commit_line {
+                out.push('\n');
+            }
+        }
+
+        // graph_show_commit_msg
+        let mut rest = text;
+        loop {
+            match rest.find('\n') {
+                Some(pos) => {
+                    out.push_str(&rest[..=pos]);
+                    rest = &rest[pos + 1..];
+                    if rest.is_empty() {
+                        break;
+                    }
+                    out.push_str(&self.next_line().0);
+                }
+                None => {
+                    out.push_str(rest);
+                    break;
+                }
+            }
+        }
+
+        let newline_terminated = text.ends_with('\n');
+        if !self.is_commit_finished() {
+            if !newline_terminated {
+                out.push('\n');
+            }
+            loop {
+                out.push_str(&self.next_line().0);
+                if self.is_commit_finished() {
+                    break;
+                }
+                out.push('\n');
+            }
+            if newline_terminated {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn update_state(&mut self, state: State) {
+        self.prev_state = self.state;
+        self.state = state;
+    }
+
+    fn is_current(&self, id: &Id) -> bool {
+        self.commit.as_ref() == Some(id)
+    }
+
+    fn num_parents(&self) -> usize {
+        self.parents.len()
+    }
+
+    fn num_dashed_parents(&self) -> usize {
+        (self.num_parents() as isize + self.merge_layout - 3).max(0) as usize
+    }
+
+    fn num_expansion_rows(&self) -> usize {
+        self.num_dashed_parents() * 2
+    }
+
+    fn needs_pre_commit_line(&self) -> bool {
+        self.num_parents() >= 3
+            && self.commit_index + 1 < self.columns.len()
+            && self.expansion_row < self.num_expansion_rows()
+    }
+
+    fn increment_column_color(&mut self) {
+        self.default_column_color = (self.default_column_color + 1) % COLUMN_COLORS;
+    }
+
+    fn current_column_color(&self) -> usize {
+        if self.colors.enabled() {
+            self.default_column_color
+        } else {
+            COLUMN_COLORS
+        }
+    }
+
+    fn find_commit_color(&self, commit: &Id) -> usize {
+        self.columns
+            .iter()
+            .find(|c| &c.commit == commit)
+            .map(|c| c.color)
+            .unwrap_or_else(|| self.current_column_color())
+    }
+
+    fn find_new_column_by_commit(&self, commit: &Id) -> Option<usize> {
+        self.new_columns.iter().position(|c| &c.commit == commit)
+    }
+
+    fn insert_into_new_columns(&mut self, commit: &Id, idx: Option<usize>) {
+        let i = match self.find_new_column_by_commit(commit) {
+            Some(i) => i,
+            None => {
+                let color = self.find_commit_color(commit);
+                self.new_columns.push(Column {
+                    commit: commit.clone(),
+                    color,
+                });
+                self.new_columns.len() - 1
+            }
+        };
+
+        let mapping_idx;
+        if self.num_parents() > 1 && idx.is_some() && self.merge_layout == -1 {
+            // First parent of a merge: lean the merge edges depending on whether
+            // the parent sits in a column left of the merge.
+            let dist = idx.unwrap_or(0) as isize - i as isize;
+            let shift = if dist > 1 { 2 * dist - 3 } else { 1 };
+            self.merge_layout = if dist > 0 { 0 } else { 1 };
+            self.edges_added = self.num_parents() as isize + self.merge_layout - 2;
+            mapping_idx = (self.width as isize + (self.merge_layout - 1) * shift) as usize;
+            self.width += 2 * self.merge_layout as usize;
+        } else if self.edges_added > 0
+            && self.width >= 2
+            && self.mapping[self.width - 2] == Some(i)
+        {
+            // The merge's new edge joins the last existing column right away.
+            mapping_idx = self.width - 2;
+            self.edges_added = -1;
+        } else {
+            mapping_idx = self.width;
+            self.width += 2;
+        }
+        self.mapping[mapping_idx] = Some(i);
+    }
+
+    fn update_columns(&mut self) {
+        std::mem::swap(&mut self.columns, &mut self.new_columns);
+        self.new_columns.clear();
+
+        let max_new_columns = self.columns.len() + self.num_parents();
+        self.mapping_size = 2 * max_new_columns;
+        self.mapping = vec![None; self.mapping_size];
+        if self.old_mapping.len() < self.mapping_size {
+            self.old_mapping.resize(self.mapping_size, None);
+        }
+
+        self.width = 0;
+        self.prev_edges_added = self.edges_added;
+        self.edges_added = 0;
+
+        let mut seen_this = false;
+        let mut is_commit_in_columns = true;
+        let num_columns = self.columns.len();
+        for i in 0..=num_columns {
+            let col_commit = if i == num_columns {
+                if seen_this {
+                    break;
+                }
+                is_commit_in_columns = false;
+                self.commit.clone().expect("graph commit")
+            } else {
+                self.columns[i].commit.clone()
+            };
+
+            if self.is_current(&col_commit) {
+                seen_this = true;
+                self.commit_index = i;
+                self.merge_layout = -1;
+                let parents = self.parents.clone();
+                for parent in &parents {
+                    if self.num_parents() > 1 || !is_commit_in_columns {
+                        self.increment_column_color();
+                    }
+                    self.insert_into_new_columns(parent, Some(i));
+                }
+                // A commit always occupies at least two characters.
+                if self.num_parents() == 0 {
+                    self.width += 2;
+                }
+            } else {
+                self.insert_into_new_columns(&col_commit, None);
+            }
+        }
+
+        while self.mapping_size > 1 && self.mapping[self.mapping_size - 1].is_none() {
+            self.mapping_size -= 1;
+        }
+    }
+
+    fn is_mapping_correct(&self) -> bool {
+        self.mapping[..self.mapping_size]
+            .iter()
+            .enumerate()
+            .all(|(i, target)| target.is_none_or(|t| t == i / 2))
+    }
+
+    fn pad_horizontally(&self, line: &mut Line<'_>) {
+        if line.width < self.width {
+            let n = self.width - line.width;
+            line.push_n(' ', n);
+        }
+    }
+
+    fn output_padding_line(&self, line: &mut Line<'_>) {
+        for column in &self.new_columns {
+            line.write_column(column, '|');
+            line.push(' ');
+        }
+    }
+
+    fn output_skip_line(&mut self, line: &mut Line<'_>) {
+        line.push_str("...");
+        if self.needs_pre_commit_line() {
+            self.update_state(State::PreCommit);
+        } else {
+            self.update_state(State::Commit);
+        }
+    }
+
+    fn output_pre_commit_line(&mut self, line: &mut Line<'_>) {
+        let mut seen_this = false;
+        for (i, column) in self.columns.iter().enumerate() {
+            if self.is_current(&column.commit) {
+                seen_this = true;
+                line.write_column(column, '|');
+                line.push_n(' ', self.expansion_row);
+            } else if seen_this && self.expansion_row == 0 {
+                // Lines after a previous merge were drawn as '\'; keep them so.
+                if self.prev_state == State::PostMerge && self.prev_commit_index < i {
+                    line.write_column(column, '\\');
+                } else {
+                    line.write_column(column, '|');
+                }
+            } else if seen_this {
+                line.write_column(column, '\\');
+            } else {
+                line.write_column(column, '|');
+            }
+            line.push(' ');
+        }
+
+        self.expansion_row += 1;
+        if !self.needs_pre_commit_line() {
+            self.update_state(State::Commit);
+        }
+    }
+
+    fn output_commit_char(&self, line: &mut Line<'_>) {
+        line.push_str(&self.mark);
+    }
+
+    fn draw_octopus_merge(&self, line: &mut Line<'_>) {
+        let dashed_parents = self.num_dashed_parents();
+        for i in 0..dashed_parents {
+            let j = self.mapping[(self.commit_index + i + 2) * 2].expect("octopus edge mapping");
+            let column = &self.new_columns[j];
+            line.write_column(column, '-');
+            line.write_column(column, if i == dashed_parents - 1 { '.' } else { '-' });
+        }
+    }
+
+    fn output_commit_line(&mut self, line: &mut Line<'_>) {
+        let mut seen_this = false;
+        let num_columns = self.columns.len();
+        for i in 0..=num_columns {
+            let is_commit = if i == num_columns {
+                if seen_this {
+                    break;
+                }
+                true
+            } else {
+                self.is_current(&self.columns[i].commit)
+            };
+
+            if is_commit {
+                seen_this = true;
+                self.output_commit_char(line);
+                if self.num_parents() > 2 {
+                    self.draw_octopus_merge(line);
+                }
+            } else {
+                let column = &self.columns[i];
+                if seen_this && self.edges_added > 1 {
+                    line.write_column(column, '\\');
+                } else if seen_this && self.edges_added == 1 {
+                    // A right-skewed 2-way or left-skewed 3-way merge has no
+                    // pre-commit rows; continue a previous merge's '\' edges.
+                    if self.prev_state == State::PostMerge
+                        && self.prev_edges_added > 0
+                        && self.prev_commit_index < i
+                    {
+                        line.write_column(column, '\\');
+                    } else {
+                        line.write_column(column, '|');
+                    }
+                } else if self.prev_state == State::Collapsing
+                    && self.old_mapping.get(2 * i + 1).copied().flatten() == Some(i)
+                    && self.mapping.get(2 * i).copied().flatten().is_some_and(|t| t < i)
+                {
+                    line.write_column(column, '/');
+                } else {
+                    line.write_column(column, '|');
+                }
+            }
+            line.push(' ');
+        }
+
+        if self.num_parents() > 1 {
+            self.update_state(State::PostMerge);
+        } else if self.is_mapping_correct() {
+            self.update_state(State::Padding);
+        } else {
+            self.update_state(State::Collapsing);
+        }
+    }
+
+    fn output_post_merge_line(&mut self, line: &mut Line<'_>) {
+        let mut seen_this = false;
+        let mut parent_col: Option<usize> = None;
+        let first_parent = self.parents.first().cloned();
+        let num_columns = self.columns.len();
+
+        for i in 0..=num_columns {
+            let is_commit = if i == num_columns {
+                if seen_this {
+                    break;
+                }
+                true
+            } else {
+                self.is_current(&self.columns[i].commit)
+            };
+
+            if is_commit {
+                // Draw each parent's edge in the color of its new column.
+                seen_this = true;
+                let mut idx = self.merge_layout.max(0) as usize;
+                for (j, parent) in self.parents.iter().enumerate() {
+                    let par_column = self
+                        .find_new_column_by_commit(parent)
+                        .expect("merge parent column");
+                    line.write_column(&self.new_columns[par_column], MERGE_CHARS[idx]);
+                    if idx == 2 {
+                        if self.edges_added > 0 || j + 1 < self.num_parents() {
+                            line.push(' ');
+                        }
+                    } else {
+                        idx += 1;
+                    }
+                }
+                if self.edges_added == 0 {
+                    line.push(' ');
+                }
+            } else {
+                let column = &self.columns[i];
+                if seen_this {
+                    if self.edges_added > 0 {
+                        line.write_column(column, '\\');
+                    } else {
+                        line.write_column(column, '|');
+                    }
+                    line.push(' ');
+                } else {
+                    line.write_column(column, '|');
+                    if self.merge_layout != 0 || i + 1 != self.commit_index {
+                        match parent_col {
+                            Some(p) => line.write_column(&self.columns[p], '_'),
+                            None => line.push(' '),
+                        }
+                    }
+                }
+            }
+
+            let col_commit = if i == num_columns {
+                self.commit.as_ref()
+            } else {
+                Some(&self.columns[i].commit)
+            };
+            if i < num_columns && col_commit == first_parent.as_ref() {
+                parent_col = Some(i);
+            }
+        }
+
+        if self.is_mapping_correct() {
+            self.update_state(State::Padding);
+        } else {
+            self.update_state(State::Collapsing);
+        }
+    }
+
+    fn output_collapsing_line(&mut self, line: &mut Line<'_>) {
+        let mut used_horizontal = false;
+        let mut horizontal_edge: Option<usize> = None;
+        let mut horizontal_edge_target: Option<usize> = None;
+
+        std::mem::swap(&mut self.mapping, &mut self.old_mapping);
+        let size = self.mapping_size;
+        if self.mapping.len() < size {
+            self.mapping.resize(size, None);
+        }
+        for slot in &mut self.mapping[..size] {
+            *slot = None;
+        }
+
+        for i in 0..size {
+            let Some(target) = self.old_mapping[i] else {
+                continue;
+            };
+            // Branch lines only ever move left.
+            debug_assert!(target * 2 <= i);
+
+            if target * 2 == i {
+                self.mapping[i] = Some(target);
+            } else if self.mapping[i - 1].is_none() {
+                // Nothing to the left: move one step left.
+                self.mapping[i - 1] = Some(target);
+                if horizontal_edge.is_none() {
+                    horizontal_edge = Some(i);
+                    horizontal_edge_target = Some(target);
+                    let mut j = target * 2 + 3;
+                    while j + 2 < i {
+                        self.mapping[j] = Some(target);
+                        j += 2;
+                    }
+                }
+            } else if self.mapping[i - 1] == Some(target) {
+                // Joins the line to our left, which shares our parent.
+            } else {
+                // Cross over the line to our left.
+                self.mapping[i - 2] = Some(target);
+                if horizontal_edge.is_none() {
+                    horizontal_edge_target = Some(target);
+                    horizontal_edge = Some(i - 1);
+                    let mut j = target * 2 + 3;
+                    while j + 2 < i {
+                        self.mapping[j] = Some(target);
+                        j += 2;
+                    }
+                }
+            }
+        }
+
+        self.old_mapping[..size].copy_from_slice(&self.mapping[..size]);
+
+        if self.mapping[self.mapping_size - 1].is_none() {
+            self.mapping_size -= 1;
+        }
+
+        for i in 0..self.mapping_size {
+            match self.mapping[i] {
+                None => line.push(' '),
+                Some(target) if target * 2 == i => {
+                    line.write_column(&self.new_columns[target], '|');
+                }
+                Some(target)
+                    if Some(target) == horizontal_edge_target
+                        && Some(i) != horizontal_edge.map(|e| e - 1) =>
+                {
+                    // Only the first segment of a horizontal edge carries on.
+                    if i != target * 2 + 3 {
+                        self.mapping[i] = None;
+                    }
+                    used_horizontal = true;
+                    line.write_column(&self.new_columns[target], '_');
+                }
+                Some(target) => {
+                    if used_horizontal && horizontal_edge.is_some_and(|e| i < e) {
+                        self.mapping[i] = None;
+                    }
+                    line.write_column(&self.new_columns[target], '/');
+                }
+            }
+        }
+
+        if self.is_mapping_correct() {
+            self.update_state(State::Padding);
+        }
+    }
+}
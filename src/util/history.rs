@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use gix::object::tree::diff::{Action, Change};
+use gix::objs::tree::{EntryKind, EntryMode};
+use gix::{ObjectId as Oid, Repository};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::convert::Infallible;
+use std::path::Path;
+
+use crate::util::walk::Limit;
+
+/// A commit kept by path-limited history, with its parents rewritten to the
+/// nearest kept ancestors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub id: Oid,
+    pub parents: Vec<Oid>,
+    /// Paths to show changes for: the pathspec, or with `--follow` the file's
+    /// name at this commit (both names where it was renamed).
+    pub paths: Vec<String>,
+}
+
+/// What the walk learned about one commit.
+struct Node {
+    time: i64,
+    /// Parents left after simplification; a commit TREESAME to a parent keeps only that one.
+    kept: Vec<Oid>,
+    /// Not TREESAME: the commit changes one of the paths.
+    shown: bool,
+    paths: Vec<String>,
+}
+
+/// Pathspec of literal repository-relative paths. An empty path stands for the
+/// whole tree, which is absent while the tree is empty.
+struct Paths {
+    paths: Vec<String>,
+    /// `--follow`: a single path, renamed to its old name when a commit turns out
+    /// to have created it by renaming another file.
+    follow: bool,
+}
+
+impl Paths {
+    fn entry(&self, tree: &gix::Tree, path: &str) -> Option<(Oid, EntryMode)> {
+        if path.is_empty() {
+            return (!tree.data.is_empty()).then(|| (tree.id, EntryKind::Tree.into()));
+        }
+        tree.lookup_entry_by_path(Path::new(path))
+            .ok()
+            .flatten()
+            .map(|entry| (entry.object_id(), entry.mode()))
+    }
+
+    fn any_present(&self, tree: &gix::Tree) -> bool {
+        self.paths.iter().any(|p| self.entry(tree, p).is_some())
+    }
+
+    fn same(&self, a: &gix::Tree, b: &gix::Tree) -> bool {
+        self.paths.iter().all(|p| self.entry(a, p) == self.entry(b, p))
+    }
+
+    /// With `--follow`, switch to the old name if `tree` created the followed
+    /// path by renaming a file from `parent_tree`.
+    fn follow_rename(&mut self, parent_tree: &gix::Tree, tree: &gix::Tree) -> Result<()> {
+        if !self.follow || self.entry(parent_tree, &self.paths[0]).is_some() {
+            return Ok(());
+        }
+        let mut renamed_from = None;
+        parent_tree
+            .changes()?
+            .options(|opts| {
+                opts.track_path().track_rewrites(Some(Default::default()));
+            })
+            .for_each_to_obtain_tree(tree, |change| {
+                if let Change::Rewrite { source_location, location, copy: false, .. } = change {
+                    if location == self.paths[0].as_str() {
+                        renamed_from = Some(source_location.to_string());
+                        return Ok::<_, Infallible>(Action::Cancel);
+                    }
+                }
+                Ok(Action::Continue)
+            })
+            .context("Failed to diff trees")?;
+        if let Some(old_path) = renamed_from {
+            self.paths[0] = old_path;
+        }
+        Ok(())
+    }
+}
+
+/// History of `starts` limited to commits that change `paths`, simplified the
+/// way `git log -- <paths>` does by default:
+///
+/// - a commit TREESAME to a parent (same entries at every path) follows only
+///   that parent, so side branches that brought no change are pruned;
+/// - TREESAME commits are hidden, and the parents of the commits that remain
+///   are rewritten to skip them;
+/// - a root commit is shown only if one of the paths exists in it.
+///
+/// With a `limit`, only the commits it selects are walked.
+///
+/// Entries come in topological order, newest commit date first among commits
+/// whose children have all been emitted (git's `--date-order`). Simplifying
+/// needs every commit's parents settled first, so the whole history is walked,
+/// reading each commit and its trees from the object database, before the
+/// first entry is returned.
+pub fn path_limited(
+    repo: &Repository,
+    starts: &[Oid],
+    limit: Option<&Limit>,
+    paths: Vec<String>,
+    follow: bool,
+    first_parent: bool,
+) -> Result<Vec<Entry>> {
+    let mut paths = Paths { paths, follow };
+    let mut nodes: HashMap<Oid, Node> = HashMap::new();
+    // Commits in the order the walk reached them, for stable tie-breaking below.
+    let mut walked = Vec::new();
+
+    // Newest first; among equal dates, first queued first.
+    let mut queue = BinaryHeap::new();
+    let mut counter = 0usize;
+    let mut queued = HashSet::new();
+    let mut enqueue = |queue: &mut BinaryHeap<(i64, Reverse<usize>, Oid)>, id: Oid, time: i64| {
+        if queued.insert(id) {
+            queue.push((time, Reverse(counter), id));
+            counter += 1;
+        }
+    };
+    let selected = |id: &Oid| limit.is_none_or(|limit| limit.contains(id));
+    for &id in starts.iter().filter(|id| selected(id)) {
+        let commit = repo.find_commit(id)?;
+        enqueue(&mut queue, id, commit.time()?.seconds);
+    }
+
+    while let Some((time, _, id)) = queue.pop() {
+        let commit = repo.find_commit(id)?;
+        let tree = commit.tree()?;
+        let max_parents = if first_parent { 1 } else { usize::MAX };
+        let parents = commit
+            .parent_ids()
+            .take(max_parents)
+            .map(|parent| Ok(parent.object()?.try_into_commit()?))
+            .collect::<Result<Vec<_>>>()?;
+        let mut names = paths.paths.clone();
+
+        let (mut kept, shown) = if parents.is_empty() {
+            (Vec::new(), paths.any_present(&tree))
+        } else {
+            let mut treesame_parent = None;
+            for parent in &parents {
+                let parent_tree = parent.tree()?;
+                if paths.same(&parent_tree, &tree) {
+                    treesame_parent = Some(parent.id);
+                    break;
+                }
+                paths.follow_rename(&parent_tree, &tree)?;
+            }
+            match treesame_parent {
+                Some(parent) => (vec![parent], false),
+                None => (parents.iter().map(|p| p.id).collect(), true),
+            }
+        };
+
+        kept.retain(selected);
+        for parent in &parents {
+            if kept.contains(&parent.id) {
+                enqueue(&mut queue, parent.id, parent.time()?.seconds);
+            }
+        }
+        if names != paths.paths {
+            names.extend(paths.paths.iter().cloned());
+        }
+        nodes.insert(id, Node { time, kept, shown, paths: names });
+        walked.push(id);
+    }
+
+    let order = date_topo_order(&nodes, &walked);
+    let mut rewritten: HashMap<Oid, Option<Oid>> = HashMap::new();
+    let mut entries = Vec::new();
+    for id in order {
+        if !nodes[&id].shown {
+            continue;
+        }
+        let mut parents = Vec::new();
+        for &parent in &nodes[&id].kept {
+            if let Some(target) = rewrite(&nodes, &mut rewritten, parent)? {
+                if !parents.contains(&target) {
+                    parents.push(target);
+                }
+            }
+        }
+        entries.push(Entry { id, parents, paths: nodes[&id].paths.clone() });
+    }
+    Ok(entries)
+}
+
+/// Nearest shown commit at or below `id` along kept parents, if any.
+fn rewrite(
+    nodes: &HashMap<Oid, Node>,
+    memo: &mut HashMap<Oid, Option<Oid>>,
+    id: Oid,
+) -> Result<Option<Oid>> {
+    let mut chain = Vec::new();
+    let mut current = Some(id);
+    let result = loop {
+        let Some(at) = current else { break None };
+        if let Some(&known) = memo.get(&at) {
+            break known;
+        }
+        let node = nodes.get(&at).context("Commit missing from path-limited walk")?;
+        if node.shown {
+            break Some(at);
+        }
+        chain.push(at);
+        current = node.kept.first().copied();
+    };
+    for at in chain {
+        memo.insert(at, result);
+    }
+    Ok(result)
+}
+
+/// Kahn's algorithm over the walked commits, popping the newest commit whose
+/// children have all been emitted, as git's `sort_in_topological_order` does
+/// for `--date-order`.
+fn date_topo_order(nodes: &HashMap<Oid, Node>, walked: &[Oid]) -> Vec<Oid> {
+    let mut indegree: HashMap<Oid, usize> = walked.iter().map(|&id| (id, 0)).collect();
+    for id in walked {
+        for parent in &nodes[id].kept {
+            if let Some(count) = indegree.get_mut(parent) {
+                *count += 1;
+            }
+        }
+    }
+
+    let mut counter = 0usize;
+    let mut queue = BinaryHeap::new();
+    for &id in walked {
+        if indegree[&id] == 0 {
+            queue.push((nodes[&id].time, Reverse(counter), id));
+            counter += 1;
+        }
+    }
+
+    let mut order = Vec::with_capacity(walked.len());
+    while let Some((_, _, id)) = queue.pop() {
+        for parent in &nodes[&id].kept {
+            if let Some(count) = indegree.get_mut(parent) {
+                *count -= 1;
+                if *count == 0 {
+                    queue.push((nodes[parent].time, Reverse(counter), *parent));
+                    counter += 1;
+                }
+            }
+        }
+        order.push(id);
+    }
+    order
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Runs repository hooks the way `git commit` does: from `core.hooksPath` or
+/// `$GIT_COMMON_DIR/hooks`, with the worktree root as working directory and
+/// `GIT_INDEX_FILE` pointing at the index being committed.
+pub struct Hooks {
+    dir: PathBuf,
+    workdir: PathBuf,
+    index_file: PathBuf,
+}
+
+impl Hooks {
+    pub fn from_repo(repo: &gix::Repository) -> Result<Self> {
+        let workdir = repo
+            .work_dir()
+            .context("Repository has no working directory")?
+            .to_path_buf();
+
+        let configured = repo
+            .config_snapshot()
+            .trusted_path("core.hooksPath")
+            .transpose()
+            .context("Failed to interpolate core.hooksPath")?
+            .map(|p| p.into_owned());
+
+        let dir = match configured {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => workdir.join(path),
+            None => repo.common_dir().join("hooks"),
+        };
+
+        Ok(Self {
+            dir,
+            workdir,
+            index_file: repo.index_path(),
+        })
+    }
+
+    /// Path of the hook called `name` if it exists and is executable.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        let path = self.dir.join(name);
+        is_executable(&path).then_some(path)
+    }
+
+    /// Run hook `name` with `args`.
+    ///
+    /// Returns `Ok(false)` when no such hook is installed. Hook output goes
+    /// to stderr; a non-zero exit is reported as an error.
+    pub fn run(&self, name: &str, args: &[&OsStr]) -> Result<bool> {
+        let Some(path) = self.find(name) else {
+            return Ok(false);
+        };
+
+        // As git does, the hook's stdout joins our stderr and its stderr
+        // passes through, so its messages appear in order, as they are written.
+        let status = Command::new(&path)
+            .args(args)
+            .current_dir(&self.workdir)
+            .env("GIT_INDEX_FILE", &self.index_file)
+            .env("GIT_EDITOR", ":")
+            .stdout(stderr_for_child()?)
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("Failed to run {name} hook: {}", path.display()))?;
+
+        if !status.success() {
+            let status = status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "signal".to_string());
+            anyhow::bail!("{name} hook failed (exit status {status})");
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// A handle on our stderr for a child's stdout.
+#[cfg(unix)]
+fn stderr_for_child() -> Result<Stdio> {
+    use std::os::fd::AsFd;
+    Ok(Stdio::from(std::io::stderr().as_fd().try_clone_to_owned()?))
+}
+
+#[cfg(windows)]
+fn stderr_for_child() -> Result<Stdio> {
+    use std::os::windows::io::AsHandle;
+    Ok(Stdio::from(std::io::stderr().as_handle().try_clone_to_owned()?))
+}
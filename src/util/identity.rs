@@ -0,0 +1,71 @@
+use gix::Repository;
+
+/// Fallback identity used when nothing is configured and policy allows it.
+pub const FALLBACK_NAME: &str = "agt";
+pub const FALLBACK_EMAIL: &str = "agt@local";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Author,
+    Committer,
+}
+
+impl Role {
+    fn env_prefix(self) -> &'static str {
+        match self {
+            Role::Author => "GIT_AUTHOR",
+            Role::Committer => "GIT_COMMITTER",
+        }
+    }
+
+    /// `author` or `committer`, also the config section holding overrides.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Author => "author",
+            Role::Committer => "committer",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+impl Identity {
+    /// `Name <email>`, as used in trailers and signing-key lookup.
+    pub fn to_ident(&self) -> String {
+        format!("{} <{}>", self.name, self.email)
+    }
+}
+
+/// Resolve the identity for `role` with git's precedence:
+/// `GIT_<ROLE>_NAME`/`GIT_<ROLE>_EMAIL`, then `<role>.name`/`<role>.email`, then
+/// `user.name`/`user.email`, and finally `EMAIL` for the address.
+///
+/// Returns `None` when either part is missing.
+pub fn resolve(repo: &Repository, role: Role) -> Option<Identity> {
+    let config = repo.config_snapshot();
+    let lookup = |field: &str| -> Option<String> {
+        env_value(&format!("{}_{}", role.env_prefix(), field.to_uppercase()))
+            .or_else(|| {
+                config
+                    .string(format!("{}.{field}", role.as_str()).as_str())
+                    .map(|v| v.to_string())
+            })
+            .or_else(|| config.string(format!("user.{field}").as_str()).map(|v| v.to_string()))
+            .filter(|v| !v.trim().is_empty())
+    };
+
+    let name = lookup("name")?;
+    let email = lookup("email").or_else(|| env_value("EMAIL"))?;
+    Some(Identity {
+        name: name.trim().to_string(),
+        email: email.trim().trim_start_matches('<').trim_end_matches('>').to_string(),
+    })
+}
+
+fn env_value(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
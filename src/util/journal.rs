@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Append-only record of the commits GitCommit created, one object id per line,
+/// kept at `$GIT_COMMON_DIR/git-nope/commits`.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(common_dir: &Path) -> Self {
+        Self {
+            path: common_dir.join("git-nope").join("commits"),
+        }
+    }
+
+    pub fn record(&self, id: &gix::oid) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{id}")?;
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &gix::oid) -> bool {
+        let wanted = id.to_string();
+        std::fs::read_to_string(&self.path)
+            .map(|content| content.lines().any(|line| line.trim() == wanted))
+            .unwrap_or(false)
+    }
+}
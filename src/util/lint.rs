@@ -0,0 +1,208 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Types accepted when Conventional Commits is enabled without an explicit allowlist.
+pub const DEFAULT_CONVENTIONAL_TYPES: &[&str] = &[
+    "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
+];
+
+/// Commit message rules. Each rule is inactive until configured.
+#[derive(Debug, Clone, Default)]
+pub struct LintRules {
+    /// Require a `type(scope)!: subject` header.
+    pub conventional: bool,
+    /// Allowed Conventional Commit types (empty means [`DEFAULT_CONVENTIONAL_TYPES`]).
+    pub types: Vec<String>,
+    /// Allowed scopes (empty means any scope, or none).
+    pub scopes: Vec<String>,
+    pub subject_max_length: Option<usize>,
+    pub no_trailing_period: bool,
+    /// Maximum body line length. Lines without spaces (URLs, paths) are exempt.
+    pub body_wrap: Option<usize>,
+    /// The message must contain a match somewhere, e.g. `#\d+` or `[A-Z]+-\d+`.
+    pub issue_pattern: Option<Regex>,
+    /// Case-insensitive phrases that may not appear anywhere in the message.
+    pub banned_phrases: Vec<String>,
+}
+
+impl LintRules {
+    /// Whether any rule is configured. The blank line after the subject is
+    /// checked whenever one is.
+    pub fn is_active(&self) -> bool {
+        self.conventional
+            || !self.types.is_empty()
+            || !self.scopes.is_empty()
+            || self.subject_max_length.is_some()
+            || self.no_trailing_period
+            || self.body_wrap.is_some()
+            || self.issue_pattern.is_some()
+            || !self.banned_phrases.is_empty()
+    }
+}
+
+/// A single rule violation. `line` and `column` are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub rule: &'static str,
+    pub detail: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(col)) => write!(f, "{line}:{col}")?,
+            (Some(line), None) => write!(f, "{line}")?,
+            _ => write!(f, "message")?,
+        }
+        write!(f, ": [{}] {}", self.rule, self.detail)
+    }
+}
+
+fn header_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^(?P<type>[A-Za-z]+)(?:\((?P<scope>[^()]*)\))?(?P<bang>!)?: (?P<subject>\S.*)$")
+            .expect("valid header regex")
+    })
+}
+
+pub fn lint_message(message: &str, rules: &LintRules) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let lines: Vec<&str> = message.lines().collect();
+    let header = lines.first().copied().unwrap_or("");
+
+    let mut push = |line: Option<usize>, column: Option<usize>, rule, detail: String| {
+        violations.push(Violation {
+            line,
+            column,
+            rule,
+            detail,
+        })
+    };
+
+    if rules.conventional {
+        match header_regex().captures(header) {
+            None => push(
+                Some(1),
+                Some(1),
+                "conventional",
+                "header must look like `type(scope): subject`".to_string(),
+            ),
+            Some(caps) => {
+                let kind = &caps["type"];
+                let allowed = if rules.types.is_empty() {
+                    DEFAULT_CONVENTIONAL_TYPES.contains(&kind)
+                } else {
+                    rules.types.iter().any(|t| t == kind)
+                };
+                if !allowed {
+                    let list = if rules.types.is_empty() {
+                        DEFAULT_CONVENTIONAL_TYPES.join(", ")
+                    } else {
+                        rules.types.join(", ")
+                    };
+                    push(
+                        Some(1),
+                        Some(1),
+                        "type",
+                        format!("type `{kind}` is not allowed (expected one of: {list})"),
+                    );
+                }
+                if let Some(scope) = caps.name("scope") {
+                    if !rules.scopes.is_empty()
+                        && !rules.scopes.iter().any(|s| s == scope.as_str())
+                    {
+                        push(
+                            Some(1),
+                            Some(scope.start() + 1),
+                            "scope",
+                            format!(
+                                "scope `{}` is not allowed (expected one of: {})",
+                                scope.as_str(),
+                                rules.scopes.join(", ")
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(max) = rules.subject_max_length {
+        let len = header.chars().count();
+        if len > max {
+            push(
+                Some(1),
+                Some(max + 1),
+                "subject-length",
+                format!("subject is {len} characters (max {max})"),
+            );
+        }
+    }
+
+    if rules.no_trailing_period && header.trim_end().ends_with('.') {
+        push(
+            Some(1),
+            Some(header.trim_end().chars().count()),
+            "subject-period",
+            "subject must not end with a period".to_string(),
+        );
+    }
+
+    if rules.is_active() && lines.len() > 1 && !lines[1].trim().is_empty() {
+        push(
+            Some(2),
+            Some(1),
+            "body-separator",
+            "body must be separated from the subject by a blank line".to_string(),
+        );
+    }
+
+    if let Some(wrap) = rules.body_wrap {
+        for (idx, line) in lines.iter().enumerate().skip(1) {
+            let len = line.chars().count();
+            if len > wrap && line.trim().contains(' ') {
+                push(
+                    Some(idx + 1),
+                    Some(wrap + 1),
+                    "body-wrap",
+                    format!("line is {len} characters (wrap at {wrap})"),
+                );
+            }
+        }
+    }
+
+    if let Some(pattern) = &rules.issue_pattern {
+        if !pattern.is_match(message) {
+            push(
+                None,
+                None,
+                "issue-reference",
+                format!("no issue reference matching `{}`", pattern.as_str()),
+            );
+        }
+    }
+
+    for phrase in &rules.banned_phrases {
+        let needle = phrase.to_lowercase();
+        if needle.is_empty() {
+            continue;
+        }
+        for (idx, line) in lines.iter().enumerate() {
+            if let Some(pos) = line.to_lowercase().find(&needle) {
+                let column = line.to_lowercase()[..pos].chars().count() + 1;
+                push(
+                    Some(idx + 1),
+                    Some(column),
+                    "banned-phrase",
+                    format!("`{phrase}` is not allowed in commit messages"),
+                );
+            }
+        }
+    }
+
+    violations
+}
@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::io::Read;
+
+use crate::util::trailers::{merge_trailers, Trailer};
+
+/// How a commit message is tidied before use, mirroring `git commit --cleanup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cleanup {
+    /// Use the message exactly as given.
+    #[default]
+    Verbatim,
+    /// Strip trailing whitespace, leading/trailing blank lines and collapse blank runs.
+    Whitespace,
+    /// Like `Whitespace`, and also drop lines starting with `#`.
+    Strip,
+}
+
+impl Cleanup {
+    pub fn parse(mode: &str) -> Result<Self> {
+        match mode {
+            "verbatim" => Ok(Cleanup::Verbatim),
+            "whitespace" => Ok(Cleanup::Whitespace),
+            "strip" => Ok(Cleanup::Strip),
+            other => anyhow::bail!("Invalid cleanup mode: {other} (expected verbatim, whitespace or strip)"),
+        }
+    }
+
+    pub fn apply(self, message: &str) -> String {
+        if self == Cleanup::Verbatim {
+            return message.to_string();
+        }
+
+        let mut lines: Vec<&str> = Vec::new();
+        for line in message.lines() {
+            if self == Cleanup::Strip && line.starts_with('#') {
+                continue;
+            }
+            let line = line.trim_end();
+            if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+                continue;
+            }
+            lines.push(line);
+        }
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+}
+
+/// Read a message from `path`, or from stdin when `path` is `-`.
+pub fn read_message_source(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut buf = String::new();
+        std::io::stdin()
+            .read_to_string(&mut buf)
+            .context("Failed to read commit message from stdin")?;
+        Ok(buf)
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read message file: {path}"))
+    }
+}
+
+/// Commit message handed over as JSON, so orchestrators avoid shell quoting:
+/// `{"subject": "...", "body": "...", "trailers": [{"key": "Refs", "value": "#12"}]}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StructuredMessage {
+    pub subject: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub trailers: Vec<Trailer>,
+}
+
+impl StructuredMessage {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let parsed: Self = serde_json::from_str(json).context("Invalid JSON commit message")?;
+        if parsed.subject.trim().is_empty() {
+            anyhow::bail!("JSON commit message has an empty subject");
+        }
+        if parsed.subject.contains('\n') {
+            anyhow::bail!("JSON commit message subject must be a single line");
+        }
+        Ok(parsed)
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = self.subject.trim().to_string();
+        if let Some(body) = self.body.as_deref().map(str::trim).filter(|b| !b.is_empty()) {
+            out.push_str("\n\n");
+            out.push_str(body);
+        }
+        merge_trailers(&out, &self.trailers)
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::Result;
+use std::io::{self, BufWriter, IsTerminal, Stdout, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Environment git gives the pager unless the user set it: `less` quits when
+/// the output fits one screen, passes colors through and keeps the screen;
+/// `lv` passes colors through.
+const PAGER_ENV: &[(&str, &str)] = &[("LESS", "FRX"), ("LV", "-c")];
+
+/// Characters that make git hand a pager command to the shell rather than
+/// run it directly (`SHELL_META_CHARS` in git's run-command.c). Whitespace is
+/// among them, so only a bare program name is run without `sh -c`.
+const SHELL_METACHARACTERS: &[char] = &[
+    '|', '&', ';', '<', '>', '(', ')', '$', '`', '\\', '"', '\'', ' ', '\t', '\n', '*', '?', '[', '#', '~', '=', '%',
+];
+
+/// Where a command's output goes: the user's pager when stdout is a
+/// terminal, stdout otherwise. Output is buffered either way; call
+/// [`Output::finish`] once done.
+pub enum Output {
+    Stdout(BufWriter<Stdout>),
+    Pager { input: BufWriter<ChildStdin>, child: Child },
+}
+
+impl Output {
+    /// Page the output of `command` (the name its `pager.<command>` setting
+    /// uses) unless `no_pager` (`--no-pager`) or stdout is not a terminal.
+    /// A pager that cannot be started falls back to stdout.
+    pub fn start(repo: Option<&gix::Repository>, command: &str, no_pager: bool) -> Self {
+        let stdout = || Output::Stdout(BufWriter::new(io::stdout()));
+        if no_pager || !io::stdout().is_terminal() {
+            return stdout();
+        }
+        let config = repo.map(|repo| repo.config_snapshot());
+        let Some(pager) = pager_command(config.as_ref(), command) else {
+            return stdout();
+        };
+        match spawn(&pager) {
+            Some(mut child) => match child.stdin.take() {
+                Some(stdin) => Output::Pager { input: BufWriter::new(stdin), child },
+                None => stdout(),
+            },
+            None => stdout(),
+        }
+    }
+
+    /// Flush the output and wait for the pager to exit. A reader that went
+    /// away early, such as a pager the user quit or `| head`, is not an
+    /// error: `result` failing with a broken pipe counts as success, as git
+    /// treats the `SIGPIPE` it dies of.
+    pub fn finish(self, result: Result<()>) -> Result<()> {
+        let flushed = match self {
+            Output::Stdout(mut out) => out.flush(),
+            Output::Pager { mut input, mut child } => {
+                let flushed = input.flush();
+                // Close the pipe so the pager sees the end of the output.
+                drop(input);
+                let _ = child.wait();
+                flushed
+            }
+        };
+        match result.and(flushed.map_err(Into::into)) {
+            Err(err) if is_broken_pipe(&err) => Ok(()),
+            other => other,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Pager { input, .. } => input.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Pager { input, .. } => input.flush(),
+        }
+    }
+}
+
+/// The pager git would run for `command`, in git's order of precedence:
+/// `GIT_PAGER`, then `pager.<command>` when it names a program, then
+/// `core.pager`, then `PAGER`, then `less`. `None` when paging is off: an
+/// empty command or `cat`, or `pager.<command>` set to false.
+pub fn pager_command(config: Option<&gix::config::Snapshot>, command: &str) -> Option<String> {
+    let key = format!("pager.{command}");
+    let mut per_command = None;
+    if let Some(config) = config {
+        match config.try_boolean(key.as_str()) {
+            Some(Ok(false)) => return None,
+            Some(Ok(true)) | None => {}
+            // Not a boolean, so it names the pager.
+            Some(Err(_)) => per_command = config.string(key.as_str()).map(|p| p.to_string()),
+        }
+    }
+    let pager = std::env::var("GIT_PAGER")
+        .ok()
+        .or(per_command)
+        .or_else(|| config.and_then(|config| config.string("core.pager")).map(|p| p.to_string()))
+        .or_else(|| std::env::var("PAGER").ok())
+        .unwrap_or_else(|| "less".to_string());
+    (!pager.trim().is_empty() && pager != "cat").then_some(pager)
+}
+
+/// Start `pager` with its input piped. A bare program name is run directly;
+/// anything with arguments, quoting or other shell syntax is run by `sh -c`,
+/// as git does.
+fn spawn(pager: &str) -> Option<Child> {
+    let mut command = if pager.contains(SHELL_METACHARACTERS) {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(pager);
+        command
+    } else {
+        Command::new(pager)
+    };
+    for (name, value) in PAGER_ENV {
+        if std::env::var_os(name).is_none() {
+            command.env(name, value);
+        }
+    }
+    command.stdin(Stdio::piped()).spawn().ok()
+}
+
+/// Whether writing failed because the reader closed the pipe.
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
@@ -0,0 +1,755 @@
+use anyhow::Result;
+use git2::{Diff, DiffOptions, Repository, Tree};
+use std::collections::BTreeMap;
+
+use crate::util::color::ColorConfig;
+use crate::util::diffstat::{numstat_lines, stat_lines, stats_from_diff};
+
+/// Lines of context around changes, as git's default `-U3`.
+const CONTEXT: usize = 3;
+
+/// Which change summaries to print for a commit, in the order `git log` prints
+/// them when several are requested.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OutputFormat {
+    pub name_status: bool,
+    pub numstat: bool,
+    pub stat: bool,
+    pub patch: bool,
+}
+
+impl OutputFormat {
+    pub fn any(&self) -> bool {
+        self.name_status || self.numstat || self.stat || self.patch
+    }
+}
+
+/// Diff `old` (or the empty tree for a root commit) against `new`, limited to
+/// `pathspec` when it is non-empty, with rename detection.
+pub fn tree_diff<'r>(
+    repo: &'r Repository,
+    old: Option<&Tree>,
+    new: &Tree,
+    pathspec: &[String],
+) -> Result<Diff<'r>> {
+    let mut opts = DiffOptions::new();
+    opts.indent_heuristic(true);
+    // An empty path stands for the whole tree.
+    if !pathspec.iter().any(String::is_empty) {
+        for path in pathspec {
+            opts.pathspec(path);
+        }
+    }
+    let mut diff = repo.diff_tree_to_tree(old, Some(new), Some(&mut opts))?;
+    diff.find_similar(None)?;
+    Ok(diff)
+}
+
+/// Render `diff` the way `git log` does after a commit header: name-status,
+/// numstat and stat lines, then a blank line and the patch. Lines carry no
+/// trailing newline; `width` is the room left for `--stat` after any graph.
+pub fn render(
+    repo: &Repository,
+    diff: &Diff,
+    format: OutputFormat,
+    width: usize,
+    colors: &ColorConfig,
+) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    if diff.deltas().len() == 0 {
+        return Ok(lines);
+    }
+    if format.name_status {
+        lines.extend(name_status_lines(diff)?);
+    }
+    if format.numstat || format.stat {
+        let stats = stats_from_diff(repo, diff)?;
+        if format.numstat {
+            lines.extend(numstat_lines(&stats));
+        }
+        if format.stat {
+            lines.extend(stat_lines(&stats, width, colors));
+        }
+    }
+    if format.patch {
+        if format.name_status || format.numstat || format.stat {
+            lines.push(String::new());
+        }
+        lines.extend(patch_lines(diff, colors)?);
+    }
+    Ok(lines)
+}
+
+/// `M<TAB>path`, or `R086<TAB>old<TAB>new` for renames and copies. git2 only
+/// exposes the similarity score through the raw format, so the status is cut
+/// out of its `:<mode> <mode> <id> <id> <status><TAB>...` lines.
+fn name_status_lines(diff: &Diff) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    diff.print(git2::DiffFormat::Raw, |delta, _, line| {
+        let content = String::from_utf8_lossy(line.content());
+        let Some(status) = content.split('\t').next().and_then(|head| head.split(' ').nth(4)) else {
+            return true;
+        };
+        let path = |file: git2::DiffFile| {
+            file.path()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        lines.push(match delta.status() {
+            git2::Delta::Renamed | git2::Delta::Copied => {
+                format!("{status}\t{}\t{}", path(delta.old_file()), path(delta.new_file()))
+            }
+            git2::Delta::Deleted => format!("{status}\t{}", path(delta.old_file())),
+            _ => format!("{status}\t{}", path(delta.new_file())),
+        });
+        true
+    })?;
+    Ok(lines)
+}
+
+/// Unified diff lines, colored like git: headers bold, hunk ranges cyan,
+/// additions green, deletions red.
+fn patch_lines(diff: &Diff, colors: &ColorConfig) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_, _, line| {
+        let content = String::from_utf8_lossy(line.content());
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        match line.origin() {
+            'F' => {
+                for header in content.lines() {
+                    lines.push(colors.paint(colors.bold_style(), header).into_owned());
+                }
+            }
+            'H' => {
+                // "@@ -a,b +c,d @@ function context": only the ranges are colored.
+                let end = content[2..].find("@@").map_or(content.len(), |pos| pos + 4);
+                let (ranges, rest) = content.split_at(end);
+                lines.push(format!("{}{rest}", colors.paint(colors.cyan_style(), ranges)));
+            }
+            '+' => lines.push(colors.paint(colors.green_style(), format!("+{content}")).into_owned()),
+            '-' => lines.push(colors.paint(colors.red_style(), format!("-{content}")).into_owned()),
+            ' ' => lines.push(format!(" {content}")),
+            // "\ No newline at end of file" markers arrive with a leading newline.
+            '=' | '>' | '<' => lines.push(content.trim_start_matches('\n').to_string()),
+            _ => lines.extend(content.lines().map(str::to_string)),
+        }
+        true
+    })?;
+    Ok(lines)
+}
+
+/// One side of a combined diff: a path's blob in a parent or in the merge.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Side {
+    id: git2::Oid,
+    mode: u32,
+}
+
+/// A path that differs from every parent of a merge.
+struct CombinedPath {
+    path: String,
+    result: Option<Side>,
+    parents: Vec<Option<Side>>,
+}
+
+impl CombinedPath {
+    /// `A`, `D` or `M` against each parent, as `git log --cc --name-status` prints.
+    fn statuses(&self) -> String {
+        self.parents
+            .iter()
+            .map(|parent| match (parent, self.result) {
+                (None, _) => 'A',
+                (_, None) => 'D',
+                _ => 'M',
+            })
+            .collect()
+    }
+}
+
+/// Render a merge like `git log --cc`: stat and numstat against the first
+/// parent, name-status per parent, and a dense combined patch that only shows
+/// hunks where the result differs from every parent.
+pub fn render_combined(
+    repo: &Repository,
+    parents: &[Tree],
+    tree: &Tree,
+    pathspec: &[String],
+    format: OutputFormat,
+    width: usize,
+    colors: &ColorConfig,
+) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    if format.numstat || format.stat {
+        let diff = tree_diff(repo, parents.first(), tree, pathspec)?;
+        let stats_format = OutputFormat {
+            numstat: format.numstat,
+            stat: format.stat,
+            ..OutputFormat::default()
+        };
+        lines.extend(render(repo, &diff, stats_format, width, colors)?);
+    }
+
+    let paths = combined_paths(repo, parents, tree, pathspec)?;
+    if paths.is_empty() {
+        return Ok(lines);
+    }
+    if format.name_status {
+        for path in &paths {
+            lines.push(format!("{}\t{}", path.statuses(), path.path));
+        }
+    }
+    if format.patch {
+        if format.name_status || format.numstat || format.stat {
+            lines.push(String::new());
+        }
+        // Line masks hold a bit per parent and two marks in a u64.
+        if parents.len() > MAX_COMBINED_PARENTS {
+            eprintln!(
+                "Warning: combined diff is limited to {MAX_COMBINED_PARENTS} parents; showing the patch against the first parent"
+            );
+            let diff = tree_diff(repo, parents.first(), tree, pathspec)?;
+            lines.extend(patch_lines(&diff, colors)?);
+            return Ok(lines);
+        }
+        for path in &paths {
+            lines.extend(combined_patch(repo, path, colors)?);
+        }
+    }
+    Ok(lines)
+}
+
+/// Most parents a combined patch can be computed for.
+const MAX_COMBINED_PARENTS: usize = 62;
+
+/// Paths changed against every parent, in path order.
+fn combined_paths(
+    repo: &Repository,
+    parents: &[Tree],
+    tree: &Tree,
+    pathspec: &[String],
+) -> Result<Vec<CombinedPath>> {
+    let mut found: BTreeMap<String, CombinedPath> = BTreeMap::new();
+    for (n, parent) in parents.iter().enumerate() {
+        let mut opts = DiffOptions::new();
+        if !pathspec.iter().any(String::is_empty) {
+            for path in pathspec {
+                opts.pathspec(path);
+            }
+        }
+        let diff = repo.diff_tree_to_tree(Some(parent), Some(tree), Some(&mut opts))?;
+        let mut changed = Vec::new();
+        for delta in diff.deltas() {
+            let side = |file: git2::DiffFile| {
+                (!file.id().is_zero()).then(|| Side {
+                    id: file.id(),
+                    mode: u32::from(file.mode()),
+                })
+            };
+            let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+                continue;
+            };
+            changed.push((
+                path.to_string_lossy().into_owned(),
+                side(delta.old_file()),
+                side(delta.new_file()),
+            ));
+        }
+
+        if n == 0 {
+            for (path, old, new) in changed {
+                let mut sides = vec![None; parents.len()];
+                sides[0] = old;
+                found.insert(path.clone(), CombinedPath { path, result: new, parents: sides });
+            }
+        } else {
+            let mut kept = BTreeMap::new();
+            for (path, old, _) in changed {
+                if let Some(mut entry) = found.remove(&path) {
+                    entry.parents[n] = old;
+                    kept.insert(path, entry);
+                }
+            }
+            found = kept;
+        }
+    }
+    Ok(found.into_values().collect())
+}
+
+/// A line of the merge result, with what each parent says about it.
+#[derive(Default, Clone)]
+struct SLine {
+    text: Vec<u8>,
+    /// Bit `n`: the line is not in parent `n`. Higher bits mark lines to show.
+    flag: u64,
+    /// Lines removed before this one, with the parents that had them.
+    lost: Vec<(Vec<u8>, u64)>,
+    /// Lines removed against the parent being diffed, not yet merged into `lost`.
+    plost: Vec<Vec<u8>>,
+    /// Line number in each parent where a hunk starting here begins.
+    p_lno: Vec<usize>,
+}
+
+fn blob_bytes(repo: &Repository, side: Option<Side>) -> Result<Vec<u8>> {
+    match side {
+        Some(side) => Ok(repo.find_blob(side.id)?.content().to_vec()),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8000).any(|&b| b == 0)
+}
+
+/// `diff --cc` output for one path, or nothing when dense filtering leaves no
+/// hunk and the modes agree.
+fn combined_patch(repo: &Repository, path: &CombinedPath, colors: &ColorConfig) -> Result<Vec<String>> {
+    let num_parent = path.parents.len();
+    let result = blob_bytes(repo, path.result)?;
+    let parent_blobs = path
+        .parents
+        .iter()
+        .map(|side| blob_bytes(repo, *side))
+        .collect::<Result<Vec<_>>>()?;
+    let result_mode = path.result.map_or(0, |side| side.mode);
+    let mode_differs = path
+        .parents
+        .iter()
+        .any(|side| side.map_or(0, |s| s.mode) != result_mode);
+
+    if is_binary(&result) || parent_blobs.iter().any(|blob| is_binary(blob)) {
+        let mut lines = combined_header(path, mode_differs, false, colors);
+        lines.push("Binary files differ".to_string());
+        return Ok(lines);
+    }
+
+    let result_deleted = path.result.is_none();
+    let mut sline = vec![SLine { p_lno: vec![0; num_parent], ..SLine::default() }];
+    let cnt = if result_deleted {
+        0
+    } else {
+        let mut texts: Vec<&[u8]> = result.split(|&b| b == b'\n').collect();
+        if result.ends_with(b"\n") || result.is_empty() {
+            texts.pop();
+        }
+        sline = texts
+            .iter()
+            .map(|text| SLine {
+                text: text.to_vec(),
+                p_lno: vec![0; num_parent],
+                ..SLine::default()
+            })
+            .collect();
+        texts.len()
+    };
+    // One bucket for lines lost after the last line, one trailer for p_lno.
+    sline.resize(cnt + 2, SLine { p_lno: vec![0; num_parent], ..SLine::default() });
+
+    if !result_deleted {
+        for (n, parent) in parent_blobs.iter().enumerate() {
+            combine_diff(parent, &result, &mut sline, cnt, n)?;
+        }
+    }
+
+    let show_hunks = make_hunks(&mut sline, cnt, num_parent);
+    if !show_hunks && !mode_differs {
+        return Ok(Vec::new());
+    }
+    let mut lines = combined_header(path, mode_differs, true, colors);
+    if !result_deleted {
+        dump_sline(&sline, cnt, num_parent, colors, &mut lines);
+    }
+    Ok(lines)
+}
+
+/// Diff one parent against the result with no context, recording which
+/// result lines it lacks and which of its lines the result lost.
+fn combine_diff(parent: &[u8], result: &[u8], sline: &mut [SLine], cnt: usize, n: usize) -> Result<()> {
+    let nmask = 1u64 << n;
+    let mut opts = DiffOptions::new();
+    opts.context_lines(0).interhunk_lines(0).indent_heuristic(true);
+    let (parent, result) = trim_common_tail(parent, result);
+    let patch = git2::Patch::from_buffers(parent, None, result, None, Some(&mut opts))?;
+    for h in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(h)?;
+        let nb = hunk.new_start() as usize;
+        // Removed lines hang on the result line they were removed before.
+        let bucket = if hunk.new_lines() == 0 { nb } else { nb - 1 };
+        let mut lno = nb;
+        for l in 0..line_count {
+            let line = patch.line_in_hunk(h, l)?;
+            match line.origin() {
+                '-' => {
+                    let text = line.content();
+                    let text = text.strip_suffix(b"\n").unwrap_or(text);
+                    sline[bucket].plost.push(text.to_vec());
+                }
+                '+' => {
+                    sline[lno - 1].flag |= nmask;
+                    lno += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut p_lno = 1;
+    for (lno, line) in sline.iter_mut().enumerate().take(cnt + 1) {
+        line.p_lno[n] = p_lno;
+        if !line.plost.is_empty() {
+            let plost = std::mem::take(&mut line.plost);
+            let lost = std::mem::take(&mut line.lost);
+            line.lost = coalesce_lost(lost, plost, n);
+        }
+        p_lno += line.lost.iter().filter(|(_, map)| map & nmask != 0).count();
+        if lno < cnt && line.flag & nmask == 0 {
+            p_lno += 1;
+        }
+    }
+    sline[cnt + 1].p_lno[n] = p_lno;
+    Ok(())
+}
+
+/// Drop the identical tail of both files in 1 KiB blocks, keeping the partial
+/// line at the cut, as git does before any diff without context. Besides
+/// saving work, this decides how ambiguous changes near the end align.
+fn trim_common_tail<'b>(a: &'b [u8], b: &'b [u8]) -> (&'b [u8], &'b [u8]) {
+    const BLOCK: usize = 1024;
+    let smaller = a.len().min(b.len());
+    let mut trimmed = 0;
+    while BLOCK + trimmed <= smaller
+        && a[a.len() - trimmed - BLOCK..a.len() - trimmed] == b[b.len() - trimmed - BLOCK..b.len() - trimmed]
+    {
+        trimmed += BLOCK;
+    }
+    let tail = &a[a.len() - trimmed..];
+    let recovered = tail.iter().position(|&c| c == b'\n').map_or(trimmed, |pos| pos + 1);
+    let cut = trimmed - recovered;
+    (&a[..a.len() - cut], &b[..b.len() - cut])
+}
+
+/// Merge the lines parent `n` lost into those earlier parents lost, pairing
+/// identical lines along a longest common subsequence as git does.
+fn coalesce_lost(base: Vec<(Vec<u8>, u64)>, new: Vec<Vec<u8>>, n: usize) -> Vec<(Vec<u8>, u64)> {
+    let bit = 1u64 << n;
+    if base.is_empty() {
+        return new.into_iter().map(|text| (text, bit)).collect();
+    }
+    #[derive(Clone, Copy, PartialEq)]
+    enum Dir {
+        Match,
+        New,
+        Base,
+    }
+    let (lb, ln) = (base.len(), new.len());
+    let mut lcs = vec![vec![0usize; ln + 1]; lb + 1];
+    let mut dirs = vec![vec![Dir::Base; ln + 1]; lb + 1];
+    for row in dirs.iter_mut().skip(1) {
+        row[0] = Dir::Base;
+    }
+    for dir in dirs[0].iter_mut().skip(1) {
+        *dir = Dir::New;
+    }
+    for i in 1..=lb {
+        for j in 1..=ln {
+            if base[i - 1].0 == new[j - 1] {
+                lcs[i][j] = lcs[i - 1][j - 1] + 1;
+                dirs[i][j] = Dir::Match;
+            } else if lcs[i][j - 1] >= lcs[i - 1][j] {
+                lcs[i][j] = lcs[i][j - 1];
+                dirs[i][j] = Dir::New;
+            } else {
+                lcs[i][j] = lcs[i - 1][j];
+                dirs[i][j] = Dir::Base;
+            }
+        }
+    }
+
+    let (mut i, mut j) = (lb, ln);
+    let mut merged = Vec::with_capacity(lb + ln);
+    while i != 0 || j != 0 {
+        match dirs[i][j] {
+            Dir::Match => {
+                merged.push((base[i - 1].0.clone(), base[i - 1].1 | bit));
+                i -= 1;
+                j -= 1;
+            }
+            Dir::New => {
+                merged.push((new[j - 1].clone(), bit));
+                j -= 1;
+            }
+            Dir::Base => {
+                merged.push(base[i - 1].clone());
+                i -= 1;
+            }
+        }
+    }
+    merged.reverse();
+    merged
+}
+
+fn interesting(line: &SLine, all_mask: u64) -> bool {
+    line.flag & all_mask != 0 || !line.lost.is_empty()
+}
+
+/// Back off one line when the hunk's last line is only there to carry
+/// deletions; it already acts as a context line.
+fn adjust_hunk_tail(sline: &[SLine], all_mask: u64, hunk_begin: usize, i: usize) -> usize {
+    if hunk_begin < i && sline[i - 1].flag & all_mask == 0 {
+        i - 1
+    } else {
+        i
+    }
+}
+
+fn find_next(sline: &[SLine], mark: u64, mut i: usize, cnt: usize, uninteresting: bool) -> usize {
+    while i <= cnt {
+        if (sline[i].flag & mark == 0) == uninteresting {
+            return i;
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Mark the lines to show. Dense mode drops hunks where the result simply
+/// takes one of two versions unchanged.
+fn make_hunks(sline: &mut [SLine], cnt: usize, num_parent: usize) -> bool {
+    let all_mask = (1u64 << num_parent) - 1;
+    let mark = 1u64 << num_parent;
+    for line in sline.iter_mut().take(cnt + 1) {
+        if interesting(line, all_mask) {
+            line.flag |= mark;
+        } else {
+            line.flag &= !mark;
+        }
+    }
+
+    let mut i = 0;
+    while i <= cnt {
+        while i <= cnt && sline[i].flag & mark == 0 {
+            i += 1;
+        }
+        if cnt < i {
+            break;
+        }
+        let hunk_begin = i;
+        let mut j = i + 1;
+        while j <= cnt {
+            if sline[j].flag & mark == 0 {
+                // Keep going if another interesting line follows within context.
+                let mut la = adjust_hunk_tail(sline, all_mask, hunk_begin, j);
+                la = (la + CONTEXT).min(cnt + 1);
+                let mut contin = false;
+                while j < la {
+                    la -= 1;
+                    if sline[la].flag & mark != 0 {
+                        contin = true;
+                        break;
+                    }
+                }
+                if !contin {
+                    break;
+                }
+                j = la;
+            }
+            j += 1;
+        }
+        let hunk_end = j;
+
+        // Interesting only if the changes come from different sets of parents,
+        // or the result differs from all of them.
+        let mut same_diff = 0u64;
+        let mut has_interesting = false;
+        'scan: for line in &sline[i..hunk_end] {
+            let this_diff = line.flag & all_mask;
+            if this_diff != 0 {
+                if same_diff == 0 {
+                    same_diff = this_diff;
+                } else if same_diff != this_diff {
+                    has_interesting = true;
+                    break;
+                }
+            }
+            for (_, parent_map) in &line.lost {
+                if same_diff == 0 {
+                    same_diff = *parent_map;
+                } else if same_diff != *parent_map {
+                    has_interesting = true;
+                    break 'scan;
+                }
+            }
+        }
+        if !has_interesting && same_diff != all_mask {
+            for line in &mut sline[hunk_begin..hunk_end] {
+                line.flag &= !mark;
+            }
+        }
+        i = hunk_end;
+    }
+
+    give_context(sline, cnt, num_parent)
+}
+
+/// Extend the marked lines by the context around them, joining hunks whose
+/// gap is shorter than the context.
+fn give_context(sline: &mut [SLine], cnt: usize, num_parent: usize) -> bool {
+    let all_mask = (1u64 << num_parent) - 1;
+    let mark = 1u64 << num_parent;
+    let no_pre_delete = 2u64 << num_parent;
+
+    let mut i = find_next(sline, mark, 0, cnt, false);
+    if cnt < i {
+        return false;
+    }
+    while i <= cnt {
+        let mut j = i.saturating_sub(CONTEXT);
+        while j < i {
+            if sline[j].flag & mark == 0 {
+                sline[j].flag |= no_pre_delete;
+            }
+            sline[j].flag |= mark;
+            j += 1;
+        }
+
+        loop {
+            let mut j = find_next(sline, mark, i, cnt, true);
+            if cnt < j {
+                return true;
+            }
+            let k = find_next(sline, mark, j, cnt, false);
+            j = adjust_hunk_tail(sline, all_mask, i, j);
+            if k < j + CONTEXT {
+                while j < k {
+                    sline[j].flag |= mark;
+                    j += 1;
+                }
+                i = k;
+                continue;
+            }
+            i = k;
+            let end = (j + CONTEXT).min(cnt + 1);
+            while j < end {
+                sline[j].flag |= mark;
+                j += 1;
+            }
+            break;
+        }
+    }
+    true
+}
+
+/// `diff --cc`, `index`, mode and `---`/`+++` lines for a combined path.
+fn combined_header(path: &CombinedPath, mode_differs: bool, file_header: bool, colors: &ColorConfig) -> Vec<String> {
+    let meta = |text: String| colors.paint(colors.bold_style(), text).into_owned();
+    let abbrev = |side: &Option<Side>| match side {
+        Some(side) => side.id.to_string()[..7].to_string(),
+        None => "0000000".to_string(),
+    };
+    let mut lines = vec![meta(format!("diff --cc {}", path.path))];
+    let parents: Vec<String> = path.parents.iter().map(abbrev).collect();
+    lines.push(meta(format!("index {}..{}", parents.join(","), abbrev(&path.result))));
+
+    let deleted = path.result.is_none();
+    let added = !deleted && path.parents.iter().all(Option::is_none);
+    if mode_differs {
+        if added {
+            lines.push(meta(format!("new file mode {:06o}", path.result.map_or(0, |s| s.mode))));
+        } else {
+            let modes: Vec<String> = path
+                .parents
+                .iter()
+                .map(|side| format!("{:06o}", side.map_or(0, |s| s.mode)))
+                .collect();
+            let mut line = if deleted { "deleted file mode ".to_string() } else { "mode ".to_string() };
+            line.push_str(&modes.join(","));
+            if let Some(result) = path.result {
+                line.push_str(&format!("..{:06o}", result.mode));
+            }
+            lines.push(meta(line));
+        }
+    }
+    if !file_header {
+        return lines;
+    }
+    lines.push(meta(if added { "--- /dev/null".to_string() } else { format!("--- a/{}", path.path) }));
+    lines.push(meta(if deleted { "+++ /dev/null".to_string() } else { format!("+++ b/{}", path.path) }));
+    lines
+}
+
+/// A line git would use as a hunk's function context.
+fn is_hunk_comment(text: &[u8]) -> bool {
+    text.first().is_some_and(|&c| c.is_ascii_alphabetic() || c == b'_' || c == b'$')
+}
+
+/// Print the marked lines as `@@@` hunks, one marker column per parent.
+fn dump_sline(sline: &[SLine], cnt: usize, num_parent: usize, colors: &ColorConfig, lines: &mut Vec<String>) {
+    let mark = 1u64 << num_parent;
+    let no_pre_delete = 2u64 << num_parent;
+    let markers = "@".repeat(num_parent + 1);
+    let mut lno = 0;
+    loop {
+        let mut hunk_comment: Option<&[u8]> = None;
+        while lno <= cnt && sline[lno].flag & mark == 0 {
+            if is_hunk_comment(&sline[lno].text) {
+                hunk_comment = Some(&sline[lno].text);
+            }
+            lno += 1;
+        }
+        if cnt < lno {
+            break;
+        }
+        let mut hunk_end = lno + 1;
+        while hunk_end <= cnt && sline[hunk_end].flag & mark != 0 {
+            hunk_end += 1;
+        }
+        let mut rlines = hunk_end - lno;
+        if cnt < hunk_end {
+            rlines -= 1;
+        }
+
+        let mut header = markers.clone();
+        for n in 0..num_parent {
+            let start = sline[lno].p_lno[n];
+            header.push_str(&format!(" -{},{}", start, sline[hunk_end].p_lno[n] - start));
+        }
+        header.push_str(&format!(" +{},{} {markers}", lno + 1, rlines));
+        let mut header = colors.paint(colors.cyan_style(), header).into_owned();
+        if let Some(comment) = hunk_comment {
+            // Like git: the first 40 bytes, up to (not including) the last
+            // non-blank one.
+            let comment = &comment[..comment.len().min(40)];
+            let end = comment.iter().rposition(|c| !c.is_ascii_whitespace()).unwrap_or(0);
+            if end > 0 {
+                header.push(' ');
+                header.push_str(&String::from_utf8_lossy(&comment[..end]));
+            }
+        }
+        lines.push(header);
+
+        while lno < hunk_end {
+            let sl = &sline[lno];
+            lno += 1;
+            if sl.flag & no_pre_delete == 0 {
+                for (text, parent_map) in &sl.lost {
+                    let prefix: String = (0..num_parent)
+                        .map(|n| if parent_map & (1 << n) != 0 { '-' } else { ' ' })
+                        .collect();
+                    let line = format!("{prefix}{}", String::from_utf8_lossy(text));
+                    lines.push(colors.paint(colors.red_style(), line).into_owned());
+                }
+            }
+            if cnt < lno {
+                break;
+            }
+            let prefix: String = (0..num_parent)
+                .map(|n| if sl.flag & (1 << n) != 0 { '+' } else { ' ' })
+                .collect();
+            let line = format!("{prefix}{}", String::from_utf8_lossy(&sl.text));
+            if sl.flag & (mark - 1) == 0 {
+                lines.push(line);
+            } else {
+                lines.push(colors.paint(colors.green_style(), line).into_owned());
+            }
+        }
+    }
+}
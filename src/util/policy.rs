@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use gix::Repository;
+
+use crate::util::budget::BudgetPolicy;
+use crate::util::lint::LintRules;
+use crate::util::secrets::DEFAULT_ALLOWLIST;
+
+/// Repository policy for GitCommit, read from the `nope.*` git config namespace.
+///
+/// The safety switches default to strict: hooks cannot be skipped, empty
+/// commits are refused and staged changes are scanned for secrets. Everything
+/// else is off until configured: message lint, trailers, change budgets and
+/// the identity policy, so a missing identity still falls back to
+/// `agt <agt@local>` unless `nope.identity.require` is set.
+#[derive(Debug, Clone)]
+pub struct CommitPolicy {
+    /// `nope.hooks.allowSkip`: permit `--no-verify` to bypass pre-commit and commit-msg.
+    pub allow_skip_hooks: bool,
+    /// `nope.commit.allowEmpty`: permit `--allow-empty` commits that leave the tree unchanged.
+    pub allow_empty: bool,
+    /// `nope.commit.pinnedDate`: fixed author/committer date for reproducible
+    /// pipelines, used unless `GIT_AUTHOR_DATE`/`GIT_COMMITTER_DATE` is set.
+    pub pinned_date: Option<String>,
+    /// `nope.lint.*`: commit message rules.
+    pub lint: LintRules,
+    /// `nope.trailers.*`: provenance trailers appended to every commit.
+    pub trailers: TrailerPolicy,
+    /// `nope.identity.*`: who may author and commit.
+    pub identity: IdentityPolicy,
+    /// `nope.secrets.scan`: scan staged changes for credentials (default on).
+    pub scan_secrets: bool,
+    /// `nope.secrets.allowlist`: fingerprint allowlist, relative to the worktree root.
+    pub secrets_allowlist: String,
+    /// `nope.budget.*`: change-size limits per commit.
+    pub budget: BudgetPolicy,
+}
+
+/// Restrictions on author and committer identities.
+#[derive(Debug, Clone, Default)]
+pub struct IdentityPolicy {
+    /// `nope.identity.require`: refuse to fall back to a placeholder identity.
+    pub require: bool,
+    /// `nope.identity.allowedEmail` (repeatable): exact addresses that may commit.
+    pub allowed_emails: Vec<String>,
+    /// `nope.identity.allowedDomain` (repeatable): domains whose addresses may commit.
+    pub allowed_domains: Vec<String>,
+}
+
+impl IdentityPolicy {
+    /// With no allowlist configured every address is allowed.
+    pub fn allows(&self, email: &str) -> bool {
+        if self.allowed_emails.is_empty() && self.allowed_domains.is_empty() {
+            return true;
+        }
+        let email = email.to_ascii_lowercase();
+        let domain = email.rsplit_once('@').map(|(_, d)| d).unwrap_or("");
+        self.allowed_emails.iter().any(|e| e.eq_ignore_ascii_case(&email))
+            || self
+                .allowed_domains
+                .iter()
+                .any(|d| d.trim_start_matches('@').eq_ignore_ascii_case(domain))
+    }
+}
+
+/// Which provenance trailers GitCommit appends to the message.
+#[derive(Debug, Clone, Default)]
+pub struct TrailerPolicy {
+    /// `nope.trailers.generatedBy`: add `Generated-By: git-nope/<VERSION>`.
+    pub generated_by: bool,
+    /// `nope.trailers.sessionEnv`: environment variable holding the agent session
+    /// id for `Agent-Session:` (default `GIT_NOPE_SESSION`).
+    pub session_env: String,
+    /// `nope.trailers.coAuthor` (repeatable): `Co-authored-by:` values.
+    pub co_authors: Vec<String>,
+    /// `nope.trailers.signoff`: always add `Signed-off-by:` for the committer.
+    pub signoff: bool,
+}
+
+impl CommitPolicy {
+    pub fn from_repo(repo: &Repository) -> Result<Self> {
+        let config = repo.config_snapshot();
+
+        let issue_pattern = config
+            .string("nope.lint.issuePattern")
+            .map(|p| {
+                let p = p.to_string();
+                regex::Regex::new(&p).with_context(|| format!("Invalid nope.lint.issuePattern: {p}"))
+            })
+            .transpose()?;
+
+        let lint = LintRules {
+            conventional: config.boolean("nope.lint.conventional").unwrap_or(false),
+            types: list(config.string("nope.lint.types").as_deref()),
+            scopes: list(config.string("nope.lint.scopes").as_deref()),
+            subject_max_length: usize_value(&config, "nope.lint.subjectMaxLength")?,
+            no_trailing_period: config.boolean("nope.lint.noTrailingPeriod").unwrap_or(false),
+            body_wrap: usize_value(&config, "nope.lint.bodyWrap")?,
+            issue_pattern,
+            banned_phrases: strings(&config, "nope.lint.bannedPhrase"),
+        };
+
+        let trailers = TrailerPolicy {
+            generated_by: config.boolean("nope.trailers.generatedBy").unwrap_or(false),
+            session_env: config
+                .string("nope.trailers.sessionEnv")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "GIT_NOPE_SESSION".to_string()),
+            co_authors: strings(&config, "nope.trailers.coAuthor"),
+            signoff: config.boolean("nope.trailers.signoff").unwrap_or(false),
+        };
+
+        let identity = IdentityPolicy {
+            require: config.boolean("nope.identity.require").unwrap_or(false),
+            allowed_emails: strings(&config, "nope.identity.allowedEmail"),
+            allowed_domains: strings(&config, "nope.identity.allowedDomain"),
+        };
+
+        let budget = BudgetPolicy {
+            max_files: usize_value(&config, "nope.budget.maxFiles")?,
+            max_insertions: usize_value(&config, "nope.budget.maxInsertions")?,
+            max_deletions: usize_value(&config, "nope.budget.maxDeletions")?,
+            max_lines: usize_value(&config, "nope.budget.maxLines")?,
+            exempt: strings(&config, "nope.budget.exempt"),
+            exempt_max_lines: usize_value(&config, "nope.budget.exemptMaxLines")?,
+        };
+
+        Ok(Self {
+            allow_skip_hooks: config.boolean("nope.hooks.allowSkip").unwrap_or(false),
+            allow_empty: config.boolean("nope.commit.allowEmpty").unwrap_or(false),
+            pinned_date: config.string("nope.commit.pinnedDate").map(|v| v.to_string()),
+            lint,
+            trailers,
+            identity,
+            scan_secrets: config.boolean("nope.secrets.scan").unwrap_or(true),
+            secrets_allowlist: config
+                .string("nope.secrets.allowlist")
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| DEFAULT_ALLOWLIST.to_string()),
+            budget,
+        })
+    }
+}
+
+/// Split a comma- or whitespace-separated config value.
+fn list(value: Option<&gix::bstr::BStr>) -> Vec<String> {
+    value
+        .map(|v| {
+            v.to_string()
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// All values of a multi-valued key, in config order.
+fn strings(config: &gix::config::Snapshot<'_>, key: &str) -> Vec<String> {
+    config
+        .plumbing()
+        .strings(key)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect()
+}
+
+fn usize_value(config: &gix::config::Snapshot<'_>, key: &str) -> Result<Option<usize>> {
+    match config.try_integer(key) {
+        None => Ok(None),
+        Some(value) => {
+            let value = value.with_context(|| format!("Invalid integer for {key}"))?;
+            Ok(Some(usize::try_from(value).with_context(|| format!("{key} must not be negative"))?))
+        }
+    }
+}
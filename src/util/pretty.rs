@@ -0,0 +1,330 @@
+use gix::date::Time;
+
+use crate::util::color::{git_color_escape, ColorConfig};
+use crate::util::date::DateMode;
+use crate::util::decoration::{self, Decoration};
+
+/// A commit author or committer.
+pub struct Person {
+    pub name: String,
+    pub email: String,
+    pub time: Time,
+}
+
+/// An object id, with the abbreviation git shows for it: the shortest
+/// unique prefix no shorter than `core.abbrev` allows.
+#[derive(Clone)]
+pub struct Id {
+    pub full: String,
+    pub short: String,
+}
+
+/// Everything a log format can show about one commit, independent of the
+/// library that read it.
+pub struct CommitInfo {
+    pub id: Id,
+    pub tree: Id,
+    /// Parents as shown in the log, rewritten past hidden commits.
+    pub parents: Vec<Id>,
+    pub author: Person,
+    pub committer: Person,
+    /// The raw message, normally ending with a newline.
+    pub message: String,
+    /// Refs pointing at the commit, in the order git lists them.
+    pub refs: Vec<Decoration>,
+    /// The `--left-right` mark the built-in formats put before the id when
+    /// no graph shows it.
+    pub mark: Option<&'static str>,
+    /// With `-m`, the parent the diff after this header is against.
+    pub from: Option<Id>,
+}
+
+impl CommitInfo {
+    /// The subject paragraph on one line, as `%s` shows it.
+    pub fn subject(&self) -> String {
+        let mut lines = self.message.lines().skip_while(|line| line.trim().is_empty());
+        let mut subject = Vec::new();
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+            subject.push(line.trim_end());
+        }
+        subject.join(" ")
+    }
+
+    /// The message after the subject paragraph, as `%b` shows it.
+    pub fn body(&self) -> String {
+        // 0: blank lines before the subject, 1: the subject paragraph, 2: the
+        // blank lines after it.
+        let mut stage = 0;
+        let mut offset = 0;
+        for line in self.message.split_inclusive('\n') {
+            let blank = line.trim().is_empty();
+            match stage {
+                0 if blank => {}
+                0 | 1 if !blank => stage = 1,
+                1 => stage = 2,
+                2 if blank => {}
+                _ => break,
+            }
+            offset += line.len();
+        }
+        self.message[offset..].to_string()
+    }
+}
+
+/// A `--format` string, parsed once and rendered for every commit. Supports
+/// git's common placeholders and color directives; anything else is copied
+/// through literally, as git does with placeholders it does not know.
+pub struct Template {
+    items: Vec<Item>,
+}
+
+enum Item {
+    Literal(String),
+    Field(Field),
+    /// `%C(...)`, `%Cred` and friends. `always` prints the color even when
+    /// colors are off.
+    Color { escape: String, always: bool },
+    /// `%C(auto)`: color the following `%h`, `%H`, `%d` and `%D` as git does.
+    AutoColor,
+}
+
+enum Field {
+    Hash,
+    AbbrevHash,
+    Tree,
+    AbbrevTree,
+    Parents,
+    AbbrevParents,
+    AuthorName,
+    AuthorEmail,
+    /// `%ad` in the log's date mode, or a fixed one such as `%ai`.
+    AuthorDate(Option<DateMode>),
+    CommitterName,
+    CommitterEmail,
+    CommitterDate(Option<DateMode>),
+    Subject,
+    Body,
+    RawBody,
+    Decorations,
+    RefNames,
+    /// The built-in formats' id: mark, id and `-m` parent, all in yellow.
+    Heading { abbrev: bool },
+    /// `Merge: <parents>` and a newline, for merges only.
+    MergeLine,
+    /// The message indented by four spaces, as `medium` shows it.
+    IndentedMessage,
+}
+
+impl Template {
+    pub fn parse(format: &str) -> Self {
+        let mut items = Vec::new();
+        let mut literal = String::new();
+        let mut rest = format;
+        while let Some(at) = rest.find('%') {
+            literal.push_str(&rest[..at]);
+            let after = &rest[at + 1..];
+            match placeholder(after) {
+                Some((item, used)) => {
+                    match item {
+                        Item::Literal(text) => literal.push_str(&text),
+                        item => {
+                            if !literal.is_empty() {
+                                items.push(Item::Literal(std::mem::take(&mut literal)));
+                            }
+                            items.push(item);
+                        }
+                    }
+                    rest = &after[used..];
+                }
+                None => {
+                    literal.push('%');
+                    rest = after;
+                }
+            }
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            items.push(Item::Literal(literal));
+        }
+        Self { items }
+    }
+
+    /// git's default `medium` format: commit, author, date and indented message.
+    pub fn medium() -> Self {
+        let literal = |text: &str| Item::Literal(text.to_string());
+        Self {
+            items: vec![
+                Item::AutoColor,
+                literal("commit "),
+                Item::Field(Field::Heading { abbrev: false }),
+                Item::Field(Field::Decorations),
+                literal("\n"),
+                Item::Field(Field::MergeLine),
+                literal("Author: "),
+                Item::Field(Field::AuthorName),
+                literal(" <"),
+                Item::Field(Field::AuthorEmail),
+                literal(">\nDate:   "),
+                Item::Field(Field::AuthorDate(None)),
+                literal("\n\n"),
+                Item::Field(Field::IndentedMessage),
+            ],
+        }
+    }
+
+    /// git's `oneline` format: id, decorations and subject, the id
+    /// abbreviated as `--oneline` shows it.
+    pub fn oneline(abbrev: bool) -> Self {
+        Self {
+            items: vec![
+                Item::AutoColor,
+                Item::Field(Field::Heading { abbrev }),
+                Item::Field(Field::Decorations),
+                Item::Literal(" ".to_string()),
+                Item::Field(Field::Subject),
+            ],
+        }
+    }
+
+    /// Whether the format produces no text at all, in which case git prints
+    /// no line for the commit either.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// `dates` is the mode `%ad` and `%cd` use, as git's `--date`.
+    pub fn render(&self, commit: &CommitInfo, colors: &ColorConfig, dates: &DateMode) -> String {
+        let mut out = String::new();
+        let mut auto = false;
+        for item in &self.items {
+            match item {
+                Item::Literal(text) => out.push_str(text),
+                Item::Color { escape, always } => {
+                    if *always || colors.enabled() {
+                        out.push_str(escape);
+                    }
+                }
+                Item::AutoColor => {
+                    auto = colors.enabled();
+                    if auto && !out.is_empty() {
+                        out.push_str("\x1b[m");
+                    }
+                }
+                Item::Field(field) => out.push_str(&field_text(field, commit, colors, auto, dates)),
+            }
+        }
+        out
+    }
+}
+
+/// The item for the placeholder at the start of `text` (just after a `%`)
+/// and how many bytes it spans.
+fn placeholder(text: &str) -> Option<(Item, usize)> {
+    let field = |field| Some((Item::Field(field), 1));
+    let person = |name, email, date: fn(Option<DateMode>) -> Field| {
+        let field = match text.as_bytes().get(1)? {
+            b'n' => name,
+            b'e' => email,
+            b'd' => date(None),
+            b'D' => date(Some(DateMode::Rfc)),
+            b'i' => date(Some(DateMode::Iso)),
+            b'I' => date(Some(DateMode::IsoStrict)),
+            b's' => date(Some(DateMode::Short)),
+            b't' => date(Some(DateMode::Unix)),
+            b'r' => date(Some(DateMode::Relative)),
+            _ => return None,
+        };
+        Some((Item::Field(field), 2))
+    };
+    match text.as_bytes().first()? {
+        b'%' => Some((Item::Literal("%".to_string()), 1)),
+        b'n' => Some((Item::Literal("\n".to_string()), 1)),
+        b'H' => field(Field::Hash),
+        b'h' => field(Field::AbbrevHash),
+        b'T' => field(Field::Tree),
+        b't' => field(Field::AbbrevTree),
+        b'P' => field(Field::Parents),
+        b'p' => field(Field::AbbrevParents),
+        b's' => field(Field::Subject),
+        b'b' => field(Field::Body),
+        b'B' => field(Field::RawBody),
+        b'd' => field(Field::Decorations),
+        b'D' => field(Field::RefNames),
+        b'a' => person(Field::AuthorName, Field::AuthorEmail, Field::AuthorDate),
+        b'c' => person(Field::CommitterName, Field::CommitterEmail, Field::CommitterDate),
+        b'C' => color_placeholder(&text[1..]).map(|(item, used)| (item, used + 1)),
+        _ => None,
+    }
+}
+
+/// `%C(<spec>)`, `%C(auto)`, or the short forms `%Cred`, `%Cgreen`, `%Cblue`
+/// and `%Creset`; `text` starts after the `C`.
+fn color_placeholder(text: &str) -> Option<(Item, usize)> {
+    for (name, spec) in [("red", "red"), ("green", "green"), ("blue", "blue"), ("reset", "reset")] {
+        if text.starts_with(name) {
+            let escape = git_color_escape(spec)?;
+            return Some((Item::Color { escape, always: false }, name.len()));
+        }
+    }
+    let inner = text.strip_prefix('(')?;
+    let end = inner.find(')')?;
+    let spec = &inner[..end];
+    let used = end + 2;
+    if spec == "auto" {
+        return Some((Item::AutoColor, used));
+    }
+    let (always, spec) = if let Some(spec) = spec.strip_prefix("always,") {
+        (true, spec)
+    } else {
+        (false, spec.strip_prefix("auto,").unwrap_or(spec))
+    };
+    let escape = git_color_escape(spec)?;
+    Some((Item::Color { escape, always }, used))
+}
+
+fn field_text(field: &Field, commit: &CommitInfo, colors: &ColorConfig, auto: bool, dates: &DateMode) -> String {
+    let ids = |ids: &[Id], id: fn(&Id) -> &String| ids.iter().map(id).cloned().collect::<Vec<_>>().join(" ");
+    let commit_id = |id: String| {
+        if auto {
+            colors.paint(colors.yellow_style(), id).into_owned()
+        } else {
+            id
+        }
+    };
+    match field {
+        Field::Hash => commit_id(commit.id.full.clone()),
+        Field::AbbrevHash => commit_id(commit.id.short.clone()),
+        Field::Tree => commit.tree.full.clone(),
+        Field::AbbrevTree => commit.tree.short.clone(),
+        Field::Parents => ids(&commit.parents, |id| &id.full),
+        Field::AbbrevParents => ids(&commit.parents, |id| &id.short),
+        Field::AuthorName => commit.author.name.clone(),
+        Field::AuthorEmail => commit.author.email.clone(),
+        Field::AuthorDate(mode) => mode.as_ref().unwrap_or(dates).format(&commit.author.time),
+        Field::CommitterName => commit.committer.name.clone(),
+        Field::CommitterEmail => commit.committer.email.clone(),
+        Field::CommitterDate(mode) => mode.as_ref().unwrap_or(dates).format(&commit.committer.time),
+        Field::Subject => commit.subject(),
+        Field::Body => commit.body(),
+        Field::RawBody => commit.message.clone(),
+        Field::Decorations => decoration::parenthesized(&commit.refs, auto),
+        Field::RefNames => decoration::list(&commit.refs, auto),
+        Field::Heading { abbrev: short } => {
+            let id = |id: &Id| if *short { id.short.clone() } else { id.full.clone() };
+            let mut heading = commit.mark.map(|mark| format!("{mark} ")).unwrap_or_default();
+            heading.push_str(&id(&commit.id));
+            if let Some(parent) = &commit.from {
+                heading.push_str(&format!(" (from {})", id(parent)));
+            }
+            colors.paint(colors.yellow_style(), heading).into_owned()
+        }
+        Field::MergeLine if commit.parents.len() > 1 => {
+            format!("Merge: {}\n", ids(&commit.parents, |id| &id.short))
+        }
+        Field::MergeLine => String::new(),
+        Field::IndentedMessage => commit.message.trim_end().lines().map(|line| format!("    {line}\n")).collect(),
+    }
+}
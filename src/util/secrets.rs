@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Default allowlist location, relative to the worktree root.
+pub const DEFAULT_ALLOWLIST: &str = ".nope-secrets-allowlist";
+
+/// Minimum length and Shannon entropy (bits per char) for the generic
+/// high-entropy rule. Hex digests top out at 4.0, so they never trip it.
+const ENTROPY_MIN_LEN: usize = 32;
+const ENTROPY_THRESHOLD: f64 = 4.5;
+
+/// A likely credential found in staged content. `line` is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub path: String,
+    pub line: usize,
+    pub rule: &'static str,
+    /// Stable id for allowlisting: `<path>:<rule>:<hash of the matched text>`.
+    pub fingerprint: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: [{}] fingerprint {}",
+            self.path, self.line, self.rule, self.fingerprint
+        )
+    }
+}
+
+struct Rule {
+    name: &'static str,
+    regex: Regex,
+}
+
+fn rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            (
+                "private-key",
+                r"-----BEGIN (?:RSA |DSA |EC |OPENSSH |PGP |ENCRYPTED )?PRIVATE KEY(?: BLOCK)?-----",
+            ),
+            ("aws-access-key-id", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+            (
+                "aws-secret-access-key",
+                r#"(?i)aws_?secret_?access_?key["']?\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})\b"#,
+            ),
+            ("github-token", r"\b(?:ghp|gho|ghu|ghs|ghr)_[A-Za-z0-9]{36}\b"),
+            ("github-token", r"\bgithub_pat_[A-Za-z0-9_]{82}\b"),
+            ("gcp-api-key", r"\bAIza[0-9A-Za-z_\-]{35}\b"),
+            ("gcp-service-account", r#""private_key_id"\s*:\s*"[a-f0-9]{40}""#),
+            ("slack-token", r"\bxox[abprs]-[0-9A-Za-z-]{10,}\b"),
+        ]
+        .into_iter()
+        .map(|(name, pattern)| Rule {
+            name,
+            regex: Regex::new(pattern).expect("valid secret rule"),
+        })
+        .collect()
+    })
+}
+
+fn dotenv_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?i)^\s*(?:export\s+)?[A-Z0-9_]*(?:SECRET|TOKEN|PASSWORD|PASSWD|API_?KEY|PRIVATE_?KEY|CREDENTIALS?)[A-Z0-9_]*\s*=\s*["']?([^"'\s#]{8,})"#,
+        )
+        .expect("valid dotenv rule")
+    })
+}
+
+fn candidate_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[A-Za-z0-9+/=_\-]{32,}").expect("valid entropy rule"))
+}
+
+fn is_dotenv(path: &str) -> bool {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+    name == ".env" || (name.starts_with(".env.") && !name.ends_with(".example") && !name.ends_with(".sample"))
+}
+
+fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for b in text.bytes() {
+        counts[b as usize] += 1;
+    }
+    let len = text.len() as f64;
+    counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Pattern-based scanner for credentials, with a fingerprint allowlist for
+/// known false positives.
+#[derive(Debug, Default)]
+pub struct Scanner {
+    allowlist: HashSet<String>,
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load fingerprints from `path`, one per line; `#` starts a comment.
+    /// A missing file is an empty allowlist.
+    pub fn with_allowlist_file(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        let allowlist = content
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self { allowlist })
+    }
+
+    /// Scan a whole file's content.
+    pub fn scan_text(&self, path: &str, content: &str) -> Vec<Finding> {
+        content
+            .lines()
+            .enumerate()
+            .flat_map(|(idx, line)| self.scan_line(path, idx + 1, line))
+            .collect()
+    }
+
+    /// Scan one line of `path`. Used directly when only added lines matter.
+    pub fn scan_line(&self, path: &str, line_no: usize, line: &str) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let mut report = |rule: &'static str, secret: &str| {
+            let hash = gix::objs::compute_hash(gix::hash::Kind::Sha1, gix::objs::Kind::Blob, secret.as_bytes());
+            let fingerprint = format!("{path}:{rule}:{}", &hash.to_string()[..12]);
+            if self.allowlist.contains(&fingerprint) || findings.iter().any(|f: &Finding| f.fingerprint == fingerprint) {
+                return;
+            }
+            findings.push(Finding {
+                path: path.to_string(),
+                line: line_no,
+                rule,
+                fingerprint,
+            });
+        };
+
+        let mut matched = false;
+        for rule in rules() {
+            for caps in rule.regex.captures_iter(line) {
+                let secret = caps.get(1).or_else(|| caps.get(0)).map(|m| m.as_str()).unwrap_or("");
+                report(rule.name, secret);
+                matched = true;
+            }
+        }
+
+        if is_dotenv(path) {
+            if let Some(caps) = dotenv_regex().captures(line) {
+                report("dotenv-assignment", &caps[1]);
+                matched = true;
+            }
+        }
+
+        // The generic rule only runs when nothing more specific fired, to avoid double reports.
+        if !matched {
+            for candidate in candidate_regex().find_iter(line) {
+                let text = candidate.as_str();
+                if text.len() >= ENTROPY_MIN_LEN && shannon_entropy(text) > ENTROPY_THRESHOLD {
+                    report("high-entropy-string", text);
+                }
+            }
+        }
+
+        findings
+    }
+}
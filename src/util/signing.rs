@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Signature formats understood by `gpg.format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignFormat {
+    OpenPgp,
+    Ssh,
+}
+
+/// How to sign commits, resolved from `gpg.format`, `user.signingkey` and the
+/// per-format `gpg.<format>.program` (or `gpg.program` for OpenPGP).
+#[derive(Debug, Clone)]
+pub struct Signer {
+    format: SignFormat,
+    key: Option<String>,
+    program: String,
+}
+
+impl Signer {
+    pub fn from_repo(repo: &gix::Repository) -> Result<Self> {
+        let config = repo.config_snapshot();
+        let format = match config.string("gpg.format").map(|f| f.to_string()).as_deref() {
+            None | Some("openpgp") => SignFormat::OpenPgp,
+            Some("ssh") => SignFormat::Ssh,
+            Some(other) => anyhow::bail!("Unsupported gpg.format: {other} (expected openpgp or ssh)"),
+        };
+
+        let key = config
+            .string("user.signingkey")
+            .map(|k| k.to_string())
+            .filter(|k| !k.trim().is_empty());
+
+        let program = match format {
+            SignFormat::OpenPgp => config
+                .string("gpg.openpgp.program")
+                .or_else(|| config.string("gpg.program"))
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "gpg".to_string()),
+            SignFormat::Ssh => config
+                .string("gpg.ssh.program")
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "ssh-keygen".to_string()),
+        };
+
+        Ok(Self {
+            format,
+            key,
+            program,
+        })
+    }
+
+    /// Detached, armored signature over `payload`, suitable for the `gpgsig` header.
+    ///
+    /// `committer` (`Name <email>`) is the OpenPGP key fallback when
+    /// `user.signingkey` is not set, matching git.
+    pub fn sign(&self, payload: &[u8], committer: &str) -> Result<String> {
+        let signature = match self.format {
+            SignFormat::OpenPgp => self.sign_openpgp(payload, committer)?,
+            SignFormat::Ssh => self.sign_ssh(payload)?,
+        };
+        let signature = signature.trim_end().to_string();
+        if signature.is_empty() {
+            anyhow::bail!("{} produced an empty signature", self.program);
+        }
+        Ok(signature)
+    }
+
+    fn sign_openpgp(&self, payload: &[u8], committer: &str) -> Result<String> {
+        let key = self.key.as_deref().unwrap_or(committer);
+        let mut child = Command::new(&self.program)
+            .args(["--status-fd=2", "-bsau", key])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run signing program: {}", self.program))?;
+        child
+            .stdin
+            .take()
+            .context("Failed to open signing program stdin")?
+            .write_all(payload)?;
+        let output = child.wait_with_output()?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !stderr.contains("[GNUPG:] SIG_CREATED ") {
+            anyhow::bail!("gpg failed to sign the data:\n{}", stderr.trim_end());
+        }
+        String::from_utf8(output.stdout).context("gpg signature is not valid UTF-8")
+    }
+
+    fn sign_ssh(&self, payload: &[u8]) -> Result<String> {
+        let key = self
+            .key
+            .as_deref()
+            .context("gpg.format=ssh requires user.signingkey")?;
+
+        let tmp = tempfile::Builder::new()
+            .prefix(".git_signing_buffer_")
+            .tempfile()
+            .context("Failed to create signing buffer")?;
+        std::fs::write(tmp.path(), payload)?;
+
+        // A literal public key means the private half lives in ssh-agent.
+        let literal = key.strip_prefix("key::").or_else(|| {
+            (key.starts_with("ssh-") || key.starts_with("ecdsa-") || key.starts_with("sk-")).then_some(key)
+        });
+        let _key_file;
+        let mut cmd = Command::new(&self.program);
+        cmd.args(["-Y", "sign", "-n", "git", "-f"]);
+        match literal {
+            Some(public_key) => {
+                let file = tempfile::Builder::new()
+                    .prefix(".git_signing_key_tmp")
+                    .tempfile()
+                    .context("Failed to create signing key file")?;
+                std::fs::write(file.path(), public_key)?;
+                cmd.arg(file.path()).arg("-U");
+                _key_file = file;
+            }
+            None => {
+                cmd.arg(expand_home(key));
+            }
+        }
+
+        let output = cmd
+            .arg(tmp.path())
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run signing program: {}", self.program))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} failed to sign the data:\n{}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+
+        let sig_path = PathBuf::from(format!("{}.sig", tmp.path().display()));
+        let signature = std::fs::read_to_string(&sig_path)
+            .with_context(|| format!("Failed to read SSH signature: {}", sig_path.display()));
+        let _ = std::fs::remove_file(&sig_path);
+        signature
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+/// A `Key: value` line from the trailer block at the end of a commit message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    pub fn new(key: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Keys compare case-insensitively, as `git interpret-trailers` does.
+    pub fn same_as(&self, other: &Trailer) -> bool {
+        self.key.eq_ignore_ascii_case(&other.key) && self.value.trim() == other.value.trim()
+    }
+}
+
+fn is_token(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn parse_block(paragraph: &[&str]) -> Option<Vec<Trailer>> {
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in paragraph {
+        if line.starts_with([' ', '\t']) {
+            // Continuation of the previous trailer's value.
+            let last = trailers.last_mut()?;
+            last.value.push(' ');
+            last.value.push_str(line.trim());
+            continue;
+        }
+        let (key, value) = line.split_once(':')?;
+        if !is_token(key) {
+            return None;
+        }
+        trailers.push(Trailer::new(key, value.trim()));
+    }
+    (!trailers.is_empty()).then_some(trailers)
+}
+
+/// Trailers found at the end of `message`.
+///
+/// The trailer block is the last paragraph, provided it is not the subject
+/// paragraph and every line is a `Key: value` trailer or a continuation.
+pub fn parse_trailers(message: &str) -> Vec<Trailer> {
+    trailer_block(message).unwrap_or_default()
+}
+
+fn trailer_block(message: &str) -> Option<Vec<Trailer>> {
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let start = lines.iter().rposition(|l| l.trim().is_empty())? + 1;
+    // Everything before the block must contain a non-blank line (the subject).
+    if !lines[..start].iter().any(|l| !l.trim().is_empty()) {
+        return None;
+    }
+    parse_block(&lines[start..])
+}
+
+/// Append `extra` to the message's trailer block, skipping any trailer already present.
+pub fn merge_trailers(message: &str, extra: &[Trailer]) -> String {
+    let existing = parse_trailers(message);
+    let mut added: Vec<&Trailer> = Vec::new();
+    for trailer in extra {
+        if existing.iter().any(|t| t.same_as(trailer)) || added.iter().any(|t| t.same_as(trailer)) {
+            continue;
+        }
+        added.push(trailer);
+    }
+    if added.is_empty() {
+        return message.to_string();
+    }
+
+    let mut out = message.trim_end().to_string();
+    out.push_str(if existing.is_empty() { "\n\n" } else { "\n" });
+    let lines: Vec<String> = added
+        .iter()
+        .map(|t| format!("{}: {}", t.key, t.value))
+        .collect();
+    out.push_str(&lines.join("\n"));
+    out
+}
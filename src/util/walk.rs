@@ -0,0 +1,343 @@
+use anyhow::{Context, Result};
+use gix::commitgraph::{Graph, GENERATION_NUMBER_INFINITY};
+use gix::ObjectId;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::util::git::Revisions;
+
+/// What the walk needs to know about a commit.
+struct Node {
+    parents: Vec<ObjectId>,
+    /// Committer date, in seconds.
+    time: i64,
+    /// From the commit-graph; [`GENERATION_NUMBER_INFINITY`] for commits it
+    /// does not cover.
+    generation: u32,
+}
+
+/// Commits read so far, from the commit-graph where it has them so that
+/// most commits are never decompressed.
+struct Nodes<'r> {
+    repo: &'r gix::Repository,
+    graph: Option<Graph>,
+    nodes: HashMap<ObjectId, Node>,
+}
+
+impl<'r> Nodes<'r> {
+    fn new(repo: &'r gix::Repository) -> Result<Self> {
+        let graph = repo.commit_graph_if_enabled().context("Failed to open commit-graph")?;
+        Ok(Self { repo, graph, nodes: HashMap::new() })
+    }
+
+    fn get(&mut self, id: ObjectId) -> Result<&Node> {
+        let node = match self.nodes.entry(id) {
+            Entry::Occupied(entry) => return Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry,
+        };
+        let cached = self.graph.as_ref().and_then(|graph| {
+            let commit = graph.commit_by_id(id)?;
+            let parents = commit
+                .iter_parents()
+                .map(|pos| pos.map(|pos| graph.id_at(pos).to_owned()))
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?;
+            Some(Node {
+                parents,
+                time: commit.committer_timestamp() as i64,
+                generation: commit.generation(),
+            })
+        });
+        let loaded = match cached {
+            Some(node) => node,
+            None => {
+                let commit = self
+                    .repo
+                    .find_commit(id)
+                    .with_context(|| format!("Failed to read commit {id}"))?;
+                Node {
+                    parents: commit.parent_ids().map(|id| id.detach()).collect(),
+                    time: commit.time()?.seconds,
+                    generation: GENERATION_NUMBER_INFINITY,
+                }
+            }
+        };
+        Ok(node.insert(loaded))
+    }
+}
+
+/// What painting both sides of a range learned about one commit.
+#[derive(Clone, Copy, Default)]
+struct Paint {
+    /// Reachable from an excluded commit.
+    excluded: bool,
+    /// Reachable from a left tip of `A...B`.
+    left: bool,
+    /// Queued for painting.
+    seen: bool,
+}
+
+/// How many more commits to paint once only excluded ones are left, in case
+/// skewed dates hide an included commit behind them (git's `SLOP`).
+const SLOP: usize = 5;
+
+/// The commits a range such as `main..topic` or `^v1.0 HEAD` selects: those
+/// reachable from an included commit but not from an excluded one.
+///
+/// They are found as git's `limit_list` finds them, painting both histories
+/// newest first until only excluded commits are left to visit, so shared
+/// history behind the range is not read.
+pub struct Limit {
+    commits: HashSet<ObjectId>,
+    /// Commits reachable from a left tip of `A...B`.
+    left: HashSet<ObjectId>,
+}
+
+impl Limit {
+    /// The limit `revisions` set, or `None` if they exclude nothing. With
+    /// `first_parent` only the first parents of selected commits are
+    /// followed, while exclusion still spreads to every parent, as in git.
+    pub fn new(repo: &gix::Repository, revisions: &Revisions, first_parent: bool) -> Result<Option<Self>> {
+        if revisions.exclude.is_empty() && revisions.left.is_empty() {
+            return Ok(None);
+        }
+        let mut nodes = Nodes::new(repo)?;
+        let mut paint: HashMap<ObjectId, Paint> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        let mut counter = 0usize;
+
+        let tips = revisions
+            .exclude
+            .iter()
+            .map(|&id| (id, true))
+            .chain(revisions.include.iter().map(|&id| (id, false)));
+        for (id, excluded) in tips {
+            if excluded {
+                mark_excluded(&nodes, &mut paint, id);
+            }
+            let state = paint.entry(id).or_default();
+            state.left |= revisions.left.contains(&id);
+            if !state.seen {
+                state.seen = true;
+                counter += 1;
+                queue.push((nodes.get(id)?.time, Reverse(counter), id));
+            }
+        }
+
+        let mut selected = Vec::new();
+        let mut slop = SLOP;
+        while let Some((time, _, id)) = queue.pop() {
+            let state = paint[&id];
+            let parents = nodes.get(id)?.parents.clone();
+            if state.excluded {
+                for &parent in &parents {
+                    mark_excluded(&nodes, &mut paint, parent);
+                }
+            }
+            let followed = if first_parent && !state.excluded { &parents[..parents.len().min(1)] } else { &parents[..] };
+            for &parent in followed {
+                let parent_state = paint.entry(parent).or_default();
+                parent_state.left |= state.left && !state.excluded;
+                if !parent_state.seen {
+                    parent_state.seen = true;
+                    counter += 1;
+                    queue.push((nodes.get(parent)?.time, Reverse(counter), parent));
+                }
+            }
+            if !state.excluded {
+                selected.push(id);
+                continue;
+            }
+            // Done once nothing newer waits and everything left is excluded.
+            let Some(&(newest, ..)) = queue.peek() else { break };
+            if time <= newest || queue.iter().any(|&(.., id)| !paint[&id].excluded) {
+                slop = SLOP;
+            } else {
+                slop -= 1;
+                if slop == 0 {
+                    break;
+                }
+            }
+        }
+
+        // A commit selected early may have turned out to be excluded later.
+        let commits: HashSet<_> = selected.into_iter().filter(|id| !paint[id].excluded).collect();
+        let left = commits.iter().copied().filter(|id| paint[id].left).collect();
+        Ok(Some(Self { commits, left }))
+    }
+
+    pub fn contains(&self, id: &ObjectId) -> bool {
+        self.commits.contains(id)
+    }
+
+    /// Whether `--left-right` marks the commit `<` rather than `>`.
+    pub fn is_left(&self, id: &ObjectId) -> bool {
+        self.left.contains(id)
+    }
+}
+
+/// Mark `id` excluded, and its ancestors as far as they have been read.
+fn mark_excluded(nodes: &Nodes, paint: &mut HashMap<ObjectId, Paint>, id: ObjectId) {
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        let state = paint.entry(id).or_default();
+        if state.excluded {
+            continue;
+        }
+        state.excluded = true;
+        if let Some(node) = nodes.nodes.get(&id) {
+            pending.extend(node.parents.iter().copied());
+        }
+    }
+}
+
+/// Commits reachable from a set of tips, children before parents and
+/// otherwise newest commit date first, as `git rev-list --date-order`.
+///
+/// This is git's incremental topological walk (`revision.c`): a commit is
+/// ready once every child has been emitted, and generation numbers from the
+/// commit-graph tell how deep the walk must count children before that is
+/// known. With a commit-graph the first commits come out after reading only
+/// the tips' neighbourhood; without one, the whole history is counted first.
+/// With a [`Limit`], only the commits it selects are walked.
+pub struct TopoWalk<'r> {
+    nodes: Nodes<'r>,
+    limit: Option<&'r Limit>,
+    first_parent: bool,
+    /// 1 for a commit with no pending children, plus one per pending child.
+    indegree: HashMap<ObjectId, usize>,
+    /// Commits whose parents still need counting, highest generation first.
+    indegree_queue: BinaryHeap<(u32, i64, Reverse<usize>, ObjectId)>,
+    /// Commits ready to be emitted, newest first.
+    topo_queue: BinaryHeap<(i64, Reverse<usize>, ObjectId)>,
+    /// Children of every commit at or above this generation are counted.
+    min_generation: u32,
+    /// Insertion order, which breaks ties in both queues as in git.
+    counter: usize,
+}
+
+impl<'r> TopoWalk<'r> {
+    /// Walk from `tips`; with `first_parent` only first parents are followed.
+    pub fn new(
+        repo: &'r gix::Repository,
+        tips: &[ObjectId],
+        limit: Option<&'r Limit>,
+        first_parent: bool,
+    ) -> Result<Self> {
+        let mut walk = Self {
+            nodes: Nodes::new(repo)?,
+            limit,
+            first_parent,
+            indegree: HashMap::new(),
+            indegree_queue: BinaryHeap::new(),
+            topo_queue: BinaryHeap::new(),
+            min_generation: GENERATION_NUMBER_INFINITY,
+            counter: 0,
+        };
+
+        let mut unique = Vec::new();
+        for &tip in tips {
+            if !unique.contains(&tip) && limit.is_none_or(|limit| limit.contains(&tip)) {
+                unique.push(tip);
+            }
+        }
+        for &tip in &unique {
+            let (generation, time) = walk.generation_and_time(tip)?;
+            walk.min_generation = walk.min_generation.min(generation);
+            walk.indegree.insert(tip, 1);
+            let order = walk.next_order();
+            walk.indegree_queue.push((generation, time, order, tip));
+        }
+        walk.count_children_to(walk.min_generation)?;
+
+        for &tip in &unique {
+            if walk.indegree.get(&tip) == Some(&1) {
+                let (_, time) = walk.generation_and_time(tip)?;
+                let order = walk.next_order();
+                walk.topo_queue.push((time, order, tip));
+            }
+        }
+        Ok(walk)
+    }
+
+    /// The parents of a commit the walk has reached, as recorded in the commit.
+    pub fn parents(&mut self, id: ObjectId) -> Result<&[ObjectId]> {
+        Ok(&self.nodes.get(id)?.parents)
+    }
+
+    fn next_order(&mut self) -> Reverse<usize> {
+        self.counter += 1;
+        Reverse(self.counter)
+    }
+
+    fn generation_and_time(&mut self, id: ObjectId) -> Result<(u32, i64)> {
+        let node = self.nodes.get(id)?;
+        Ok((node.generation, node.time))
+    }
+
+    /// Parents the walk follows from `id`.
+    fn followed_parents(&mut self, id: ObjectId) -> Result<Vec<ObjectId>> {
+        let (first_parent, limit) = (self.first_parent, self.limit);
+        let parents = &self.nodes.get(id)?.parents;
+        let take = if first_parent { 1 } else { usize::MAX };
+        Ok(parents
+            .iter()
+            .take(take)
+            .filter(|parent| limit.is_none_or(|limit| limit.contains(parent)))
+            .copied()
+            .collect())
+    }
+
+    /// Count the children of every commit down to `generation`, so that any
+    /// commit at or above it has its full indegree.
+    fn count_children_to(&mut self, generation: u32) -> Result<()> {
+        while let Some(&(top, ..)) = self.indegree_queue.peek() {
+            if top < generation {
+                break;
+            }
+            let (.., id) = self.indegree_queue.pop().expect("peeked");
+            for parent in self.followed_parents(id)? {
+                match self.indegree.entry(parent) {
+                    Entry::Occupied(mut entry) => *entry.get_mut() += 1,
+                    Entry::Vacant(entry) => {
+                        entry.insert(2);
+                        let (generation, time) = self.generation_and_time(parent)?;
+                        let order = self.next_order();
+                        self.indegree_queue.push((generation, time, order, parent));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit `id`: its parents lose a pending child, and those left with none
+    /// become ready.
+    fn expand(&mut self, id: ObjectId) -> Result<()> {
+        for parent in self.followed_parents(id)? {
+            let (generation, time) = self.generation_and_time(parent)?;
+            if generation < self.min_generation {
+                self.min_generation = generation;
+                self.count_children_to(generation)?;
+            }
+            let count = self.indegree.get_mut(&parent).context("Commit missing from topological walk")?;
+            *count -= 1;
+            if *count == 1 {
+                let order = self.next_order();
+                self.topo_queue.push((time, order, parent));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for TopoWalk<'_> {
+    type Item = Result<ObjectId>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, _, id) = self.topo_queue.pop()?;
+        self.indegree.insert(id, 0);
+        Some(self.expand(id).map(|()| id))
+    }
+}
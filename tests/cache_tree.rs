@@ -0,0 +1,247 @@
+mod common;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use git_nope::applets::git_commit;
+use git_nope::util::cache_tree::write_index_tree;
+
+use common::{init_git_repo, run_git, temp_root_dir, with_repo, write_file};
+
+const DIRS: usize = 200;
+const FILES_PER_DIR: usize = 100;
+
+/// Stage `DIRS * FILES_PER_DIR` index entries that all point at one blob, without
+/// touching the worktree, so the index is large but cheap to build.
+fn stage_synthetic_index(repo_dir: &Path) {
+    let blob = Command::new("git")
+        .args(["hash-object", "-w", "--stdin"])
+        .current_dir(repo_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(b"synthetic\n")?;
+            child.wait_with_output()
+        })
+        .expect("hash-object");
+    let blob = String::from_utf8(blob.stdout).expect("utf8").trim().to_string();
+
+    let mut info = String::new();
+    for d in 0..DIRS {
+        for f in 0..FILES_PER_DIR {
+            info.push_str(&format!("100644 {blob}\tdir{d:03}/file{f:03}.txt\n"));
+        }
+    }
+
+    let mut child = Command::new("git")
+        .args(["update-index", "--index-info"])
+        .current_dir(repo_dir)
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("update-index");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(info.as_bytes())
+        .expect("write index info");
+    assert!(child.wait().expect("update-index").success());
+}
+
+fn commit(repo_dir: &Path, msg: &str) {
+    let args = vec!["GitCommit".to_string(), "-m".to_string(), msg.to_string()];
+    with_repo(repo_dir, || git_commit::run(&args)).expect("commit");
+}
+
+/// The index's cache-tree, checked against the trees it names.
+fn cache_tree(repo_dir: &Path) -> gix::index::extension::Tree {
+    let repo = gix::open(repo_dir).expect("open repo");
+    let index = repo.open_index().expect("read index");
+    index.verify_extensions(true, &repo.objects).expect("valid cache-tree");
+    index.tree().expect("cache-tree extension present").clone()
+}
+
+fn assert_index_matches_head(repo_dir: &Path) {
+    let head_tree = run_git(repo_dir, &["rev-parse", "HEAD^{tree}"]);
+    let index_tree = run_git(repo_dir, &["write-tree"]);
+    assert_eq!(head_tree.stdout, index_tree.stdout);
+    run_git(repo_dir, &["diff", "--cached", "--quiet"]);
+}
+
+/// Stage `paths` with stat data well in the past, so the entries are not
+/// racily clean and the cache-tree can be saved even to a split index.
+fn stage_old_files(repo_dir: &Path, paths: &[&str], content: &str) {
+    for path in paths {
+        write_file(&repo_dir.join(path), content);
+        let out = Command::new("touch").args(["-t", "202001010000", path]).current_dir(repo_dir).output().expect("touch");
+        assert!(out.status.success());
+    }
+    run_git(repo_dir, &[&["add"], paths].concat());
+}
+
+#[test]
+fn commit_tree_reuses_unchanged_subtrees_of_large_index() {
+    let tmp = temp_root_dir();
+    let repo_dir = init_git_repo(tmp.path());
+    stage_synthetic_index(&repo_dir);
+    let repo = gix::open(&repo_dir).expect("open repo");
+
+    // Staging invalidated the cache-tree: every directory is hashed once.
+    let cold = write_index_tree(&repo).expect("write tree");
+    assert_eq!(cold.rebuilt, DIRS + 1);
+    assert!(cold.saved);
+    commit(&repo_dir, "cold");
+    assert_index_matches_head(&repo_dir);
+    let tree = cache_tree(&repo_dir);
+    assert_eq!(tree.num_entries, Some((DIRS * FILES_PER_DIR) as u32));
+    assert_eq!(tree.children.len(), DIRS);
+
+    // Nothing changed since: the whole tree comes from the cache.
+    let warm = write_index_tree(&repo).expect("write tree");
+    assert_eq!((warm.id, warm.rebuilt, warm.saved), (cold.id, 0, false));
+
+    // One changed file: only its directory and the root are hashed.
+    write_file(&repo_dir.join("dir007/file003.txt"), "changed\n");
+    run_git(&repo_dir, &["add", "dir007/file003.txt"]);
+    let changed = write_index_tree(&repo).expect("write tree");
+    assert_eq!(changed.rebuilt, 2);
+    commit(&repo_dir, "warm");
+    assert_index_matches_head(&repo_dir);
+    assert_eq!(cache_tree(&repo_dir).id, changed.id);
+}
+
+#[test]
+fn commit_keeps_split_index_and_untracked_cache() {
+    let tmp = temp_root_dir();
+    let repo_dir = init_git_repo(tmp.path());
+    run_git(&repo_dir, &["config", "core.splitIndex", "true"]);
+    run_git(&repo_dir, &["config", "core.untrackedCache", "true"]);
+    stage_old_files(&repo_dir, &["a/one.txt", "a/b/two.txt", "c/three.txt"], "one\n");
+    write_file(&repo_dir.join("untracked.txt"), "untracked\n");
+    commit(&repo_dir, "first");
+
+    // Fill the untracked cache, then change one directory. The new content
+    // differs in size, as the stat data is the same.
+    run_git(&repo_dir, &["status", "--porcelain"]);
+    stage_old_files(&repo_dir, &["a/b/two.txt"], "second\n");
+    let index_path = repo_dir.join(".git/index");
+    let before = std::fs::read(&index_path).expect("read index");
+    assert!(before.windows(4).any(|w| w == b"link"), "split index");
+    assert!(before.windows(4).any(|w| w == b"UNTR"), "untracked cache");
+
+    let written = write_index_tree(&gix::open(&repo_dir).expect("open repo")).expect("write tree");
+    assert_eq!(written.rebuilt, 3);
+    assert!(written.saved);
+    let after = std::fs::read(&index_path).expect("read index");
+    assert!(after.windows(4).any(|w| w == b"link"), "split index kept");
+    assert!(after.windows(4).any(|w| w == b"UNTR"), "untracked cache kept");
+
+    commit(&repo_dir, "second");
+    assert_index_matches_head(&repo_dir);
+    assert_eq!(cache_tree(&repo_dir).id, written.id);
+    let status = run_git(&repo_dir, &["status", "--porcelain"]);
+    assert_eq!(String::from_utf8_lossy(&status.stdout), "?? untracked.txt\n");
+}
+
+/// Stage the same changes, including `git add -N` entries, in two repos and
+/// commit one with GitCommit and the other with git.
+fn commit_with_intent_to_add(repo_dir: &Path, stock: bool) {
+    stage_old_files(repo_dir, &["a/one.txt", "b/two.txt"], "one\n");
+    run_git(repo_dir, &["commit", "-q", "-m", "base"]);
+    write_file(&repo_dir.join("a/one.txt"), "changed\n");
+    write_file(&repo_dir.join("a/new.txt"), "new\n");
+    write_file(&repo_dir.join("c/only.txt"), "only\n");
+    write_file(&repo_dir.join("top.txt"), "top\n");
+    run_git(repo_dir, &["add", "a/one.txt"]);
+    run_git(repo_dir, &["add", "-N", "a/new.txt", "c/only.txt", "top.txt"]);
+    if stock {
+        run_git(repo_dir, &["commit", "-q", "-m", "second"]);
+    } else {
+        commit(repo_dir, "second");
+    }
+}
+
+#[test]
+fn commit_with_intent_to_add_entries_matches_git() {
+    let ours_tmp = temp_root_dir();
+    let ours = init_git_repo(ours_tmp.path());
+    commit_with_intent_to_add(&ours, false);
+    let stock_tmp = temp_root_dir();
+    let stock = init_git_repo(stock_tmp.path());
+    commit_with_intent_to_add(&stock, true);
+
+    // Invalid nodes carry no tree id, so the cache-tree is not verified here.
+    let index = gix::open(&ours).expect("open repo").open_index().expect("read index");
+    let tree = index.tree().expect("cache-tree extension present");
+    assert_eq!(tree.num_entries, None, "root holding i-t-a entries is invalid");
+    let child = |name: &str| tree.children.iter().find(|child| child.name.as_slice() == name.as_bytes()).expect("child node");
+    assert_eq!(child("a").num_entries, None);
+    assert_eq!(child("b").num_entries, Some(1));
+    for args in [
+        &["rev-parse", "HEAD^{tree}"][..],
+        &["status", "--porcelain"],
+        &["diff", "--cached"],
+        &["diff-index", "--cached", "HEAD"],
+        &["ls-files", "--stage"],
+    ] {
+        let (ours, stock) = (run_git(&ours, args), run_git(&stock, args));
+        assert_eq!(String::from_utf8_lossy(&ours.stdout), String::from_utf8_lossy(&stock.stdout), "git {args:?}");
+    }
+}
+
+/// Commit twice in a repo whose index git wrote with `config`, then check
+/// the index still reads back cleanly. Returns whether the cache-tree was
+/// saved by the second commit's tree write.
+fn round_trip_git_index(config: &[(&str, &str)], index_version: Option<&str>) -> bool {
+    let tmp = temp_root_dir();
+    let repo_dir = init_git_repo(tmp.path());
+    for (key, value) in config {
+        run_git(&repo_dir, &["config", key, value]);
+    }
+    stage_old_files(&repo_dir, &["a/one.txt", "a/b/two.txt", "c/three.txt"], "one\n");
+    if let Some(version) = index_version {
+        run_git(&repo_dir, &["update-index", "--index-version", version]);
+    }
+    write_file(&repo_dir.join("untracked.txt"), "untracked\n");
+    commit(&repo_dir, "first");
+    run_git(&repo_dir, &["status", "--porcelain"]);
+
+    stage_old_files(&repo_dir, &["a/b/two.txt"], "second\n");
+    let written = write_index_tree(&gix::open(&repo_dir).expect("open repo")).expect("write tree");
+    commit(&repo_dir, "second");
+
+    assert_index_matches_head(&repo_dir);
+    run_git(&repo_dir, &["fsck", "--no-progress"]);
+    let status = run_git(&repo_dir, &["status", "--porcelain"]);
+    assert_eq!(String::from_utf8_lossy(&status.stdout), "?? untracked.txt\n");
+    let ls_files = run_git(&repo_dir, &["ls-files", "--stage"]);
+    assert_eq!(String::from_utf8_lossy(&ls_files.stdout).lines().count(), 3);
+    written.saved
+}
+
+#[test]
+fn commit_round_trips_git_written_indexes() {
+    // Path-compressed version 4 indexes are left for git to rewrite.
+    assert!(!round_trip_git_index(&[], Some("4")));
+    assert!(round_trip_git_index(&[], Some("3")));
+    assert!(round_trip_git_index(&[("core.splitIndex", "true")], None));
+    assert!(round_trip_git_index(&[("core.untrackedCache", "true")], None));
+    assert!(round_trip_git_index(&[("index.threads", "true"), ("index.recordOffsetTable", "true")], None));
+    assert!(round_trip_git_index(&[("index.recordEndOfIndexEntries", "true")], None));
+}
+
+#[test]
+fn commit_round_trips_fsmonitor_index() {
+    let tmp = temp_root_dir();
+    // Reports every path as possibly changed, so git keeps an FSMN extension
+    // without skipping any stat checks.
+    let hook = tmp.path().join("fsmonitor");
+    write_file(&hook, "#!/bin/sh\nprintf 'token\\0/\\0'\n");
+    let out = Command::new("chmod").arg("+x").arg(&hook).output().expect("chmod");
+    assert!(out.status.success());
+    let hook = hook.canonicalize().expect("hook path");
+    let hook = hook.to_str().expect("utf8 path");
+    assert!(round_trip_git_index(&[("core.fsmonitor", hook), ("core.fsmonitorHookVersion", "2")], None));
+}
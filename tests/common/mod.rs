@@ -26,8 +26,29 @@ pub fn init_git_repo(root: &Path) -> PathBuf {
     repo_dir
 }
 
+/// Variables that change who a commit is by, when it is dated and which
+/// trailers it gets. Children never inherit them from the test runner; tests
+/// that need one pass it to [`run_applet_with_env`].
+const COMMIT_ENV: &[&str] = &[
+    "GIT_AUTHOR_NAME",
+    "GIT_AUTHOR_EMAIL",
+    "GIT_AUTHOR_DATE",
+    "GIT_COMMITTER_NAME",
+    "GIT_COMMITTER_EMAIL",
+    "GIT_COMMITTER_DATE",
+    "EMAIL",
+    "GIT_NOPE_SESSION",
+];
+
+fn without_commit_env(command: &mut Command) -> &mut Command {
+    for name in COMMIT_ENV {
+        command.env_remove(name);
+    }
+    command
+}
+
 pub fn run_git(repo_dir: &Path, args: &[&str]) -> Output {
-    let out = Command::new("git")
+    let out = without_commit_env(&mut Command::new("git"))
         .args(args)
         .current_dir(repo_dir)
         .env_remove("GIT_DIR")
@@ -108,3 +129,29 @@ where
         Err(err) => std::panic::resume_unwind(err),
     }
 }
+
+/// Run the built binary as `applet` (argv[0] dispatch) inside `repo_dir`,
+/// capturing its output.
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn run_applet(repo_dir: &Path, applet: &str, args: &[&str]) -> Output {
+    run_applet_with_env(repo_dir, applet, args, &[])
+}
+
+/// [`run_applet`] with `env` set for the child only.
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn run_applet_with_env(repo_dir: &Path, applet: &str, args: &[&str], env: &[(&str, &str)]) -> Output {
+    use std::os::unix::process::CommandExt;
+
+    without_commit_env(&mut Command::new(env!("CARGO_BIN_EXE_git-nope")))
+        .arg0(applet)
+        .args(args)
+        .current_dir(repo_dir)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .env("GIT_CEILING_DIRECTORIES", repo_dir.parent().unwrap_or(repo_dir))
+        .envs(env.iter().copied())
+        .output()
+        .expect("run applet")
+}